@@ -0,0 +1,91 @@
+//! Times stock lookups and the survivorship filter on synthetic datasets, comparing the original
+//! linear scans over String names with the per-quarter Ticker index. Run with "cargo bench -p
+//! game".
+
+use std::time::Instant;
+
+use game::{DataRecord, Frequency, Quarter, StockID, Ticker, TimeID};
+
+/// The numbers of stocks in each quarter of the synthetic datasets.
+static NUM_STOCKS: [usize; 3] = [500, 2000, 5000];
+/// The number of quarters of each synthetic dataset.
+static NUM_QUARTERS: usize = 80;
+
+/// Times stock lookups and the survivorship filter on a synthetic dataset, comparing the
+/// original linear scans over String names with the per-quarter Ticker index.
+///
+/// # Arguments
+/// * `num_stocks` - The number of stocks in each synthetic quarter.
+/// * `num_quarters` - The number of synthetic quarters.
+///
+/// # Remarks
+/// The final quarter holds every other stock, so the survivorship filter removes half of the
+/// stocks from each earlier quarter.
+fn bench_lookup(num_stocks: usize, num_quarters: usize) {
+    println!("Benchmarking lookups with {} stocks over {} quarters...", num_stocks, num_quarters);
+    let names = (0..num_stocks).map(|i| format!("BENCH{}", i)).collect::<Vec<String>>();
+    let quarters = (0..num_quarters).map(|q| {
+        let time_id = TimeID {
+            year: 2000 + (q as i64) / 4,
+            period: 1 + (q as i64) % 4,
+            frequency: Frequency::Quarterly
+        };
+        let records = names.iter().enumerate().filter(|(i, _)| (q != num_quarters - 1) | (i % 2 == 0)).map(|(i, name)| DataRecord {
+            record: vec![Some(i as f64)],
+            stock_id: StockID {
+                name: Ticker::intern(name),
+                time_id: time_id,
+                iteration: 0
+            }
+        }).collect();
+        Quarter::<f64>::new(records, time_id)
+    }).collect::<Vec<_>>();
+    let string_quarters = quarters.iter().map(|quarter| {
+        quarter.iter().map(|stock| stock.stock_id.name.name().to_string()).collect::<Vec<String>>()
+    }).collect::<Vec<_>>();
+
+    // Lookup of every stock of the first quarter in the second.
+    let start = Instant::now();
+    let mut found = 0;
+    for name in &string_quarters[0] {
+        if string_quarters[1].iter().any(|other| other == name) {
+            found += 1;
+        }
+    }
+    let linear_lookup = start.elapsed();
+    let start = Instant::now();
+    let mut indexed_found = 0;
+    for stock in quarters[0].iter() {
+        if quarters[1].find_by_ticker(&stock.stock_id.name).is_some() {
+            indexed_found += 1;
+        }
+    }
+    let indexed_lookup = start.elapsed();
+    assert_eq!(found, indexed_found);
+    println!("Lookup of {} stocks: linear {:?}, indexed {:?}, speedup {:.1}x", num_stocks, linear_lookup, indexed_lookup, linear_lookup.as_secs_f64() / indexed_lookup.as_secs_f64());
+
+    // Survivorship filter against the final quarter.
+    let start = Instant::now();
+    let final_names = &string_quarters[num_quarters - 1];
+    let linear_kept = string_quarters.iter().map(|names| {
+        names.iter().filter(|name| final_names.iter().any(|final_name| final_name == *name)).cloned().collect::<Vec<String>>()
+    }).fold(0, |acc, kept| acc + kept.len());
+    let linear_filter = start.elapsed();
+    let mut indexed_quarters = quarters.clone();
+    let start = Instant::now();
+    let final_index = indexed_quarters.len() - 1;
+    let (earlier_quarters, final_quarter) = indexed_quarters.split_at_mut(final_index);
+    for quarter in earlier_quarters.iter_mut() {
+        quarter.retain(|stock| final_quarter[0].contains_ticker(&stock.stock_id.name));
+    }
+    let indexed_filter = start.elapsed();
+    let indexed_kept = indexed_quarters.iter().fold(0, |acc, quarter| acc + quarter.len());
+    assert_eq!(linear_kept, indexed_kept);
+    println!("Survivorship filter over {} quarters: linear {:?}, indexed {:?}, speedup {:.1}x", num_quarters, linear_filter, indexed_filter, linear_filter.as_secs_f64() / indexed_filter.as_secs_f64());
+}
+
+fn main() {
+    for num_stocks in NUM_STOCKS.iter() {
+        bench_lookup(*num_stocks, NUM_QUARTERS);
+    }
+}
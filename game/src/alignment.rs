@@ -0,0 +1,249 @@
+use std::{fmt, collections::{BTreeMap, HashMap}, path::PathBuf};
+use csv::Reader;
+
+use crate::data_record::TimeID;
+use crate::error::Error;
+
+static MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// How the fiscal periods of the data are moved onto the calendar periods they cover.
+///
+/// # Remarks
+/// Fiscal year Y of a company is the one that ends in its fiscal year end month of calendar year
+/// Y, so a company with a September year end reports Q1 of fiscal 2010 for October to December
+/// 2009. A record is moved to the calendar period holding the middle of the months it covers.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct AlignmentSettings {
+    /// The month, from 1 to 12, that each company's fiscal year ends in, by ticker. Companies
+    /// that aren't in the map have fiscal years that end in December.
+    pub fiscal_year_ends: HashMap<String, i64>,
+    /// A column holding the date each record's period ends, like 2010-03-31. Where it has a date
+    /// it's used in place of the fiscal year end.
+    pub period_end_field: Option<String>
+}
+
+/// What moved a record onto a calendar period.
+#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlignedBy {
+    Unchanged,
+    FiscalYearEnd,
+    PeriodEnd
+}
+
+/// The records that were moved onto other calendar periods while loading.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct AlignmentReport {
+    pub records: usize,
+    pub by_fiscal_year_end: usize,
+    pub by_period_end: usize,
+    /// The number of records moved by each number of periods, by ticker.
+    pub shifts: BTreeMap<String, BTreeMap<i64, usize>>
+}
+
+impl fmt::Display for AlignmentSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AlignmentSettings[fiscal_year_ends: {} companies, period_end_field: {:?}]", self.fiscal_year_ends.len(), self.period_end_field)
+    }
+}
+
+impl fmt::Display for AlignmentReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AlignmentReport[records: {}, remapped: {}, by_fiscal_year_end: {}, by_period_end: {}, stocks: {}]", self.records, self.remapped(), self.by_fiscal_year_end, self.by_period_end, self.shifts.len())
+    }
+}
+
+impl AlignmentSettings {
+    /// The default settings: every fiscal year ends in December, so nothing is moved.
+    pub fn new_default() -> AlignmentSettings {
+        AlignmentSettings {
+            fiscal_year_ends: HashMap::new(),
+            period_end_field: None
+        }
+    }
+    /// Returns true if no record can be moved.
+    pub fn is_default(&self) -> bool {
+        self.fiscal_year_ends.values().all(|month| *month == 12) && self.period_end_field.is_none()
+    }
+    /// A description of the settings that differs whenever they would align the data differently.
+    pub fn key(&self) -> String {
+        let mut fiscal_year_ends = self.fiscal_year_ends.iter().filter(|(_, month)| **month != 12).collect::<Vec<_>>();
+        fiscal_year_ends.sort();
+        format!("{:?} {:?}", fiscal_year_ends, self.period_end_field)
+    }
+    /// Reads a map of ticker to fiscal year end month from a CSV file with the columns "ticker"
+    /// and "fiscal_year_end". A month is written as a number from 1 to 12 or a name like "Sep".
+    ///
+    /// # Arguments
+    /// * `file_name` - The path of the file to read.
+    pub fn read_fiscal_year_ends(file_name: &str) -> Result<HashMap<String, i64>, Error> {
+        let mut reader = Reader::from_path(file_name).map_err(|why| Error::Csv(PathBuf::from(file_name), why))?;
+        let headers = reader.headers().map_err(|why| Error::Csv(PathBuf::from(file_name), why))?.clone();
+        let find_column = |name: &str| headers.iter().position(|header| header == name).ok_or_else(|| Error::Schema(format!("the fiscal year ends {:?} have no {:?} column.", file_name, name)));
+        let ticker_index = find_column("ticker")?;
+        let month_index = find_column("fiscal_year_end")?;
+        let mut fiscal_year_ends = HashMap::new();
+        for row in reader.records() {
+            let row = row.map_err(|why| Error::Csv(PathBuf::from(file_name), why))?;
+            if let (Some(ticker), Some(month)) = (row.get(ticker_index), row.get(month_index)) {
+                match parse_month(month) {
+                    Some(month) => fiscal_year_ends.insert(ticker.trim().to_string(), month),
+                    None => return Err(Error::Parse(format!("{:?} in {:?} isn't a month.", month, file_name)))
+                };
+            }
+        }
+        Ok(fiscal_year_ends)
+    }
+    /// Returns the calendar period that a record covers, and what moved it there.
+    ///
+    /// # Arguments
+    /// * `ticker` - The ticker of the record's stock.
+    /// * `time_id` - The fiscal period of the record.
+    /// * `period_end` - The value of the period end field of the record, if it has one.
+    pub fn align(&self, ticker: &str, time_id: TimeID, period_end: Option<&str>) -> Result<(TimeID, AlignedBy), String> {
+        let length = time_id.frequency.months_per_period();
+        let (last_month, aligned_by) = match period_end.map(|date| date.trim()).filter(|date| !date.is_empty()) {
+            Some(date) => (parse_date(date).ok_or_else(|| format!("the period end {:?} isn't a date like 2010-03-31", date))?, AlignedBy::PeriodEnd),
+            None => match self.fiscal_year_ends.get(ticker) {
+                Some(month) if *month != 12 => {
+                    let periods_to_year_end = time_id.frequency.periods_per_year() - time_id.period;
+                    (time_id.year * 12 + (month - 1) - length * periods_to_year_end, AlignedBy::FiscalYearEnd)
+                },
+                _ => return Ok((time_id, AlignedBy::Unchanged))
+            }
+        };
+        let aligned = TimeID::from_month(last_month - length / 2, time_id.frequency);
+        Ok((aligned, if aligned.index() == time_id.index() {AlignedBy::Unchanged} else {aligned_by}))
+    }
+}
+
+impl AlignmentReport {
+    /// Creates a report of no records.
+    pub fn new_empty() -> AlignmentReport {
+        AlignmentReport {
+            records: 0,
+            by_fiscal_year_end: 0,
+            by_period_end: 0,
+            shifts: BTreeMap::new()
+        }
+    }
+    /// Counts a record.
+    ///
+    /// # Arguments
+    /// * `ticker` - The ticker of the record's stock.
+    /// * `from` - The fiscal period of the record.
+    /// * `to` - The calendar period it was moved to.
+    /// * `aligned_by` - What moved it.
+    pub fn record(&mut self, ticker: &str, from: &TimeID, to: &TimeID, aligned_by: AlignedBy) {
+        self.records += 1;
+        match aligned_by {
+            AlignedBy::Unchanged => return,
+            AlignedBy::FiscalYearEnd => self.by_fiscal_year_end += 1,
+            AlignedBy::PeriodEnd => self.by_period_end += 1
+        }
+        *self.shifts.entry(ticker.to_string()).or_default().entry(to.index() - from.index()).or_default() += 1;
+    }
+    /// Returns the number of records moved onto another period.
+    pub fn remapped(&self) -> usize {
+        self.by_fiscal_year_end + self.by_period_end
+    }
+    /// Returns a line for each stock and shift, like "Remapped AAPL: 40 records by -1 periods."
+    pub fn lines(&self) -> Vec<String> {
+        self.shifts.iter().flat_map(|(ticker, shifts)| shifts.iter().map(move |(shift, count)| format!("Remapped {}: {} records by {} periods.", ticker, count, shift))).collect()
+    }
+}
+
+/// Parses a month written as a number from 1 to 12, or as a name like "Sep" or "September".
+fn parse_month(month: &str) -> Option<i64> {
+    let month = month.trim();
+    match month.parse::<i64>() {
+        Ok(month) if (1..=12).contains(&month) => Some(month),
+        Ok(_) => None,
+        Err(_) => MONTH_NAMES.iter().position(|name| (month.len() >= 3) && month.to_lowercase().starts_with(name)).map(|position| position as i64 + 1)
+    }
+}
+
+/// Parses a date like "2010-03-31" or "2010-03" into the number of months since the start of
+/// year zero.
+fn parse_date(date: &str) -> Option<i64> {
+    let mut parts = date.split(['-', '/']);
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.parse::<i64>().ok()?;
+    if (1..=12).contains(&month) {
+        Some(year * 12 + month - 1)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::Frequency;
+
+    fn fiscal_year_ending(month: i64) -> AlignmentSettings {
+        let mut settings = AlignmentSettings::new_default();
+        settings.fiscal_year_ends.insert("FYE".to_string(), month);
+        settings
+    }
+
+    fn assert_aligned(settings: &AlignmentSettings, fiscal: &str, frequency: Frequency, period_end: Option<&str>, expected: (i64, i64), expected_by: AlignedBy) {
+        let time_id = TimeID::from_name(fiscal).unwrap();
+        assert_eq!(time_id.frequency, frequency);
+        let (aligned, aligned_by) = settings.align("FYE", time_id, period_end).unwrap();
+        assert_eq!((aligned.year, aligned.period), expected, "{} aligned to {}", fiscal, aligned.to_string());
+        assert_eq!(aligned.frequency, frequency);
+        assert_eq!(aligned_by, expected_by);
+    }
+
+    #[test]
+    fn a_september_year_end_moves_quarters_back_a_quarter() {
+        let settings = fiscal_year_ending(9);
+        assert_aligned(&settings, "2010-Q1", Frequency::Quarterly, None, (2009, 4), AlignedBy::FiscalYearEnd);
+        assert_aligned(&settings, "2010-Q2", Frequency::Quarterly, None, (2010, 1), AlignedBy::FiscalYearEnd);
+        assert_aligned(&settings, "2010-Q4", Frequency::Quarterly, None, (2010, 3), AlignedBy::FiscalYearEnd);
+    }
+
+    #[test]
+    fn a_june_year_end_moves_years_back_a_year() {
+        let settings = fiscal_year_ending(6);
+        assert_aligned(&settings, "2010", Frequency::Annual, None, (2009, 1), AlignedBy::FiscalYearEnd);
+        let settings = fiscal_year_ending(8);
+        assert_aligned(&settings, "2010", Frequency::Annual, None, (2010, 1), AlignedBy::Unchanged);
+    }
+
+    #[test]
+    fn a_september_year_end_moves_months_back_three_months() {
+        let settings = fiscal_year_ending(9);
+        assert_aligned(&settings, "2010-M01", Frequency::Monthly, None, (2009, 10), AlignedBy::FiscalYearEnd);
+        assert_aligned(&settings, "2010-M12", Frequency::Monthly, None, (2010, 9), AlignedBy::FiscalYearEnd);
+    }
+
+    #[test]
+    fn a_period_end_overrides_the_fiscal_year_end() {
+        let settings = fiscal_year_ending(9);
+        assert_aligned(&settings, "2010-Q2", Frequency::Quarterly, Some("2010-06-30"), (2010, 2), AlignedBy::Unchanged);
+        assert_aligned(&settings, "2010-Q3", Frequency::Quarterly, Some("2010-03-31"), (2010, 1), AlignedBy::PeriodEnd);
+        assert_aligned(&settings, "2010-Q1", Frequency::Quarterly, Some(" "), (2009, 4), AlignedBy::FiscalYearEnd);
+        assert_aligned(&AlignmentSettings::new_default(), "2010-Q1", Frequency::Quarterly, Some("2010-01-31"), (2009, 4), AlignedBy::PeriodEnd);
+        assert!(settings.align("FYE", TimeID::from_name("2010-Q1").unwrap(), Some("2010-13-01")).is_err());
+    }
+
+    #[test]
+    fn december_year_ends_are_unchanged() {
+        let settings = fiscal_year_ending(12);
+        assert_aligned(&settings, "2010-Q1", Frequency::Quarterly, None, (2010, 1), AlignedBy::Unchanged);
+        assert_aligned(&AlignmentSettings::new_default(), "2010-M05", Frequency::Monthly, None, (2010, 5), AlignedBy::Unchanged);
+    }
+
+    #[test]
+    fn months_parse_by_number_or_name() {
+        assert_eq!(parse_month("9"), Some(9));
+        assert_eq!(parse_month("Sep"), Some(9));
+        assert_eq!(parse_month("september"), Some(9));
+        assert_eq!(parse_month("13"), None);
+        assert_eq!(parse_month("Se"), None);
+    }
+}
@@ -0,0 +1,484 @@
+use std::{fmt, fs, io, convert::TryInto, path::{Path, PathBuf}};
+
+use crate::data_trait::DataTrait;
+use crate::data_record::{DataRecord, Frequency, StockID, TimeID};
+use crate::error::Error;
+use crate::loading::LoadSettings;
+use crate::normalisation::Normalisation;
+use crate::percentile::PercentileSettings;
+use crate::quarter::Quarter;
+use crate::quarters::Quarters;
+use crate::ticker::Ticker;
+
+/// The first bytes of every cache file.
+static CACHE_MAGIC: &[u8; 4] = b"GAQC";
+/// The version of the cache format. Bump this whenever the layout of a cache file, or the way
+/// the cached Quarters are built, changes, so that older files are rebuilt rather than misread.
+pub static CACHE_VERSION: u32 = 3;
+
+/// A directory of binary Quarters files, built from one data directory.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Cache {
+    pub directory: PathBuf,
+    pub data_directory: PathBuf,
+    pub load_settings: LoadSettings,
+    /// The hash of the contents of the data directory and the settings it's loaded with.
+    pub data_hash: u64
+}
+
+/// A value that can be written to and read from a cache file.
+pub trait CacheValue: Sized {
+    /// Identifies the value type in the cache file header.
+    const TAG: u8;
+    /// Appends the value to a buffer.
+    fn write_value(&self, out: &mut Vec<u8>);
+    /// Reads a value written by write_value.
+    fn read_value(reader: &mut ByteReader) -> Option<Self>;
+}
+
+/// Reads little endian values from the front of a byte slice.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl CacheValue for f64 {
+    const TAG: u8 = 1;
+    fn write_value(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn read_value(reader: &mut ByteReader) -> Option<f64> {
+        reader.u64().map(f64::from_bits)
+    }
+}
+impl CacheValue for usize {
+    const TAG: u8 = 2;
+    fn write_value(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(*self as u64).to_le_bytes());
+    }
+    fn read_value(reader: &mut ByteReader) -> Option<usize> {
+        reader.u64().map(|value| value as usize)
+    }
+}
+
+impl fmt::Display for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cache[directory: {}, data_directory: {}, load_settings: {}, data_hash: {:016x}]", self.directory.display(), self.data_directory.display(), self.load_settings, self.data_hash)
+    }
+}
+
+impl<'a> ByteReader<'a> {
+    /// Creates a ByteReader at the start of a slice.
+    pub fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader {
+            bytes: bytes,
+            position: 0
+        }
+    }
+    /// Takes the next `len` bytes, or None if there aren't enough.
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.position.checked_add(len)?;
+        let taken = self.bytes.get(self.position..end)?;
+        self.position = end;
+        Some(taken)
+    }
+    /// Reads a byte.
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+    /// Reads a u32.
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+    /// Reads a u64.
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take(8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+    /// Reads an i64.
+    pub fn i64(&mut self) -> Option<i64> {
+        self.take(8).map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+    /// Reads a string written as its length in bytes followed by its UTF-8 bytes.
+    pub fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        self.take(len).and_then(|bytes| String::from_utf8(bytes.to_vec()).ok())
+    }
+    /// Returns true if every byte has been read.
+    pub fn is_finished(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+}
+
+impl Cache {
+    /// Opens a cache directory for a data directory, creating the cache directory if it doesn't
+    /// exist, and hashing the contents of the data directory.
+    ///
+    /// # Arguments
+    /// * `directory` - The cache directory.
+    /// * `data_directory` - The directory of CSV files that the Quarters are loaded from.
+    /// * `load_settings` - How the Quarters are loaded, which is part of the hash.
+    pub fn new(directory: PathBuf, data_directory: &Path, load_settings: &LoadSettings) -> Result<Cache, Error> {
+        fs::create_dir_all(&directory).map_err(|why| Error::Io(directory.clone(), why))?;
+        let mut bytes = hash_directory(data_directory).map_err(|why| Error::Io(data_directory.to_path_buf(), why))?.to_le_bytes().to_vec();
+        bytes.extend_from_slice(load_settings.key().as_bytes());
+        Ok(Cache {
+            data_hash: fnv1a(&bytes, FNV_OFFSET),
+            directory: directory,
+            data_directory: data_directory.to_path_buf(),
+            load_settings: load_settings.clone()
+        })
+    }
+    /// Loads the raw Quarters from the cache, or from the data directory if there's no valid
+    /// cache file, in which case one is written.
+    ///
+    /// # Arguments
+    /// * `iteration_max` - The number of iterations.
+    ///
+    /// # Remarks
+    /// The iteration of each stock is random, so it is reassigned after loading from the cache.
+    /// Returns an error if there's no valid cache file and the data files can't be loaded.
+    pub fn load_quarters(&self, iteration_max: usize) -> Result<Quarters<f64>, Error> {
+        let path = self.directory.join(format!("quarters-{:016x}.bin", self.data_hash));
+        match read_quarters::<f64>(&path, self.data_hash) {
+            Some(mut quarters) => {
+                println!("Loaded quarters from cache {}.", path.display());
+                quarters.assign_iterations(iteration_max);
+                Ok(quarters)
+            },
+            None => {
+                let quarters = Quarters::<f64>::new_quarters_from_directory(&self.data_directory, &self.load_settings, iteration_max)?;
+                self.store(&path, self.data_hash, &quarters);
+                Ok(quarters)
+            }
+        }
+    }
+    /// Loads the percentiled Quarters from the cache, or creates them if there's no valid cache
+    /// file, in which case one is written.
+    ///
+    /// # Arguments
+    /// * `quarters` - The raw Quarters, as returned by load_quarters.
+    /// * `gap` - The percentile gap.
+    /// * `settings` - How the percentiles are computed.
+    pub fn load_percentile_quarters(&self, quarters: &Quarters<f64>, gap: usize, settings: &PercentileSettings) -> Result<Quarters<usize>, Error> {
+        let key = self.key(quarters, &format!("percentile {} {}", gap, settings));
+        let path = self.directory.join(format!("percentile-{:016x}.bin", key));
+        self.load_transformed(&path, key, quarters, || quarters.create_percentile_quarters(gap, settings))
+    }
+    /// Loads the normalised Quarters from the cache, or creates them if there's no valid cache
+    /// file, in which case one is written.
+    ///
+    /// # Arguments
+    /// * `quarters` - The raw Quarters, as returned by load_quarters.
+    /// * `normalisation` - The Normalisation applied.
+    ///
+    /// # Remarks
+    /// Returns an error if the Normalisation can't be applied.
+    pub fn load_normalised_quarters(&self, quarters: &Quarters<f64>, normalisation: &Normalisation) -> Result<Quarters<f64>, Error> {
+        let key = self.key(quarters, &format!("normalised {}", normalisation));
+        let path = self.directory.join(format!("normalised-{:016x}.bin", key));
+        self.load_transformed(&path, key, quarters, || quarters.create_normalised_quarters(normalisation))
+    }
+    /// Loads transformed Quarters from a cache file, giving each stock the iteration it has in
+    /// the raw Quarters, or creates and stores them if the file isn't valid.
+    ///
+    /// # Remarks
+    /// Trades are valued by matching transformed records to raw records by position, so a cache
+    /// file whose stocks aren't in the same order as the raw Quarters is rebuilt.
+    fn load_transformed<T: DataTrait + CacheValue, F: FnOnce() -> Result<Quarters<T>, Error>>(&self, path: &Path, key: u64, quarters: &Quarters<f64>, create: F) -> Result<Quarters<T>, Error> {
+        match read_quarters::<T>(path, key).filter(|transformed| transformed.has_same_stocks(quarters)) {
+            Some(mut transformed) => {
+                println!("Loaded transformed quarters from cache {}.", path.display());
+                let assignment = quarters.iter().flat_map(|quarter| quarter.iter().map(|stock| (stock.stock_id.name, stock.stock_id.iteration))).collect();
+                transformed.set_iterations(&assignment);
+                Ok(transformed)
+            },
+            None => {
+                let transformed = create()?;
+                self.store(path, key, &transformed);
+                Ok(transformed)
+            }
+        }
+    }
+    /// Writes a cache file, only warning on failure since the cache is an optimisation.
+    fn store<T: DataTrait + CacheValue>(&self, path: &Path, key: u64, quarters: &Quarters<T>) {
+        match write_quarters(path, key, quarters) {
+            Ok(()) => println!("Wrote cache {}.", path.display()),
+            Err(err) => println!("Couldn't write cache {}: {}", path.display(), err)
+        }
+    }
+    /// The key of a cache file derived from some raw Quarters, described by `settings`.
+    ///
+    /// # Remarks
+    /// The raw Quarters are hashed as well as the data directory, since validation may have
+    /// changed them after they were loaded, in a way that depends on settings outside of the
+    /// LoadSettings, like the value field.
+    fn key(&self, quarters: &Quarters<f64>, settings: &str) -> u64 {
+        let mut bytes = self.data_hash.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&hash_quarters(quarters).to_le_bytes());
+        bytes.extend_from_slice(settings.as_bytes());
+        fnv1a(&bytes, FNV_OFFSET)
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Continues a 64 bit FNV-1a hash over some bytes. The hash is stable across builds and
+/// platforms, unlike the standard library's hashers.
+fn fnv1a(bytes: &[u8], mut hash: u64) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Hashes the names and contents of every file in a directory, in name order.
+///
+/// # Arguments
+/// * `directory` - The directory to hash.
+pub fn hash_directory(directory: &Path) -> io::Result<u64> {
+    let mut paths = directory.read_dir()?.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<Vec<PathBuf>>>()?;
+    paths.sort();
+    let mut hash = FNV_OFFSET;
+    for path in paths {
+        if let Some(name) = path.file_name() {
+            hash = fnv1a(name.to_string_lossy().as_bytes(), hash);
+        }
+        let contents = fs::read(&path)?;
+        hash = fnv1a(&(contents.len() as u64).to_le_bytes(), hash);
+        hash = fnv1a(&contents, hash);
+    }
+    Ok(hash)
+}
+
+/// Hashes the field names and every record of some Quarters.
+///
+/// # Arguments
+/// * `quarters` - The Quarters to hash.
+pub fn hash_quarters<T: DataTrait + CacheValue>(quarters: &Quarters<T>) -> u64 {
+    let mut out = Vec::new();
+    for field_name in &quarters.field_names {
+        write_string(&mut out, field_name);
+    }
+    let mut hash = fnv1a(&out, FNV_OFFSET);
+    for quarter in quarters.iter() {
+        for stock in quarter.iter() {
+            out.clear();
+            write_record(&mut out, stock);
+            hash = fnv1a(&out, hash);
+        }
+    }
+    hash
+}
+
+fn write_string(out: &mut Vec<u8>, string: &str) {
+    out.extend_from_slice(&(string.len() as u32).to_le_bytes());
+    out.extend_from_slice(string.as_bytes());
+}
+
+fn write_time_id(out: &mut Vec<u8>, time_id: &TimeID) {
+    out.extend_from_slice(&time_id.year.to_le_bytes());
+    out.extend_from_slice(&time_id.period.to_le_bytes());
+    out.push(time_id.frequency.periods_per_year() as u8);
+}
+
+fn write_record<T: DataTrait + CacheValue>(out: &mut Vec<u8>, stock: &DataRecord<T>) {
+    write_string(out, stock.stock_id.name.name());
+    write_time_id(out, &stock.stock_id.time_id);
+    out.extend_from_slice(&(stock.len() as u32).to_le_bytes());
+    for field in stock.iter() {
+        match field {
+            Some(value) => {
+                out.push(1);
+                value.write_value(out);
+            },
+            None => out.push(0)
+        }
+    }
+}
+
+fn read_time_id(reader: &mut ByteReader) -> Option<TimeID> {
+    Some(TimeID {
+        year: reader.i64()?,
+        period: reader.i64()?,
+        frequency: match reader.u8()? {
+            12 => Frequency::Monthly,
+            4 => Frequency::Quarterly,
+            1 => Frequency::Annual,
+            _ => return None
+        }
+    })
+}
+
+/// Writes a Quarters set to a cache file.
+///
+/// # Arguments
+/// * `path` - The cache file.
+/// * `key` - The key the file is valid for.
+/// * `quarters` - The Quarters set.
+///
+/// # Remarks
+/// The layout is the magic, version, value tag and key, followed by the field names, the
+/// starting and ending times, and each quarter as its time followed by its records. A record is
+/// its ticker name and time, followed by a presence byte and value for each field.
+pub fn write_quarters<T: DataTrait + CacheValue>(path: &Path, key: u64, quarters: &Quarters<T>) -> io::Result<()> {
+    let mut out = Vec::new();
+    out.extend_from_slice(CACHE_MAGIC);
+    out.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    out.push(T::TAG);
+    out.extend_from_slice(&key.to_le_bytes());
+    out.extend_from_slice(&(quarters.field_names.len() as u32).to_le_bytes());
+    for field_name in &quarters.field_names {
+        write_string(&mut out, field_name);
+    }
+    write_time_id(&mut out, &quarters.starting_time);
+    write_time_id(&mut out, &quarters.ending_time);
+    out.extend_from_slice(&(quarters.len() as u32).to_le_bytes());
+    for quarter in quarters.iter() {
+        write_time_id(&mut out, &quarter.time_id);
+        out.extend_from_slice(&(quarter.len() as u32).to_le_bytes());
+        for stock in quarter.iter() {
+            write_record(&mut out, stock);
+        }
+    }
+    // Write then rename, so that an interrupted write never leaves a truncated file behind
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, out)?;
+    fs::rename(&temporary, path)
+}
+
+/// Reads a Quarters set from a cache file, returning None if the file doesn't exist, isn't a
+/// cache file of this version and value type, has a different key, or is truncated.
+///
+/// # Arguments
+/// * `path` - The cache file.
+/// * `key` - The key the file must be valid for.
+///
+/// # Remarks
+/// Every stock is given iteration 0.
+pub fn read_quarters<T: DataTrait + CacheValue>(path: &Path, key: u64) -> Option<Quarters<T>> {
+    let bytes = fs::read(path).ok()?;
+    let mut reader = ByteReader::new(&bytes);
+    if (reader.take(4)? != CACHE_MAGIC) | (reader.u32()? != CACHE_VERSION) | (reader.u8()? != T::TAG) | (reader.u64()? != key) {
+        return None;
+    }
+    let field_names = (0..reader.u32()?).map(|_| reader.string()).collect::<Option<Vec<String>>>()?;
+    let starting_time = read_time_id(&mut reader)?;
+    let ending_time = read_time_id(&mut reader)?;
+    let mut quarters_vector = Vec::new();
+    for _ in 0..reader.u32()? {
+        let time_id = read_time_id(&mut reader)?;
+        let mut records = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = Ticker::intern(&reader.string()?);
+            let stock_time_id = read_time_id(&mut reader)?;
+            let mut record = Vec::new();
+            for _ in 0..reader.u32()? {
+                record.push(match reader.u8()? {
+                    0 => None,
+                    1 => Some(T::read_value(&mut reader)?),
+                    _ => return None
+                });
+            }
+            records.push(DataRecord {
+                record: record,
+                stock_id: StockID {
+                    name: name,
+                    time_id: stock_time_id,
+                    iteration: 0
+                }
+            });
+        }
+        quarters_vector.push(Quarter::new(records, time_id));
+    }
+    if !reader.is_finished() {
+        return None;
+    }
+    Some(Quarters {
+        field_names: field_names,
+        quarters_vector: quarters_vector,
+        starting_time: starting_time,
+        ending_time: ending_time
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quarters(names: &[&str]) -> Quarters<f64> {
+        let time_id = TimeID::from_name("2010-Q1").unwrap();
+        let records = names.iter().enumerate().map(|(i, name)| DataRecord {
+            record: vec![Some(i as f64), None],
+            stock_id: StockID {
+                name: Ticker::intern(name),
+                time_id: time_id,
+                iteration: 0
+            }
+        }).collect();
+        Quarters {
+            field_names: vec!["close".to_string(), "shares".to_string()],
+            quarters_vector: vec![Quarter::new(records, time_id)],
+            starting_time: time_id,
+            ending_time: time_id
+        }
+    }
+
+    fn cache(name: &str) -> Cache {
+        let directory = std::env::temp_dir().join(format!("game-cache-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&directory).unwrap();
+        Cache {
+            directory: directory.clone(),
+            data_directory: directory,
+            load_settings: LoadSettings::new_default(),
+            data_hash: 1
+        }
+    }
+
+    fn names<T: DataTrait>(quarters: &Quarters<T>) -> Vec<&str> {
+        quarters.quarters_vector[0].iter().map(|stock| stock.stock_id.name.name()).collect()
+    }
+
+    #[test]
+    fn quarters_round_trip_through_a_cache_file() {
+        let cache = cache("round-trip");
+        let path = cache.directory.join("quarters.bin");
+        let written = quarters(&["CA", "CB", "CC"]);
+        write_quarters(&path, 7, &written).unwrap();
+        let read = read_quarters::<f64>(&path, 7).unwrap();
+        assert_eq!(read.field_names, written.field_names);
+        assert_eq!(read.starting_time.index(), written.starting_time.index());
+        assert_eq!(names(&read), vec!["CA", "CB", "CC"]);
+        assert_eq!(read.quarters_vector[0].get(2).unwrap().record, vec![Some(2.0), None]);
+        assert!(read_quarters::<f64>(&path, 8).is_none());
+        assert!(read_quarters::<usize>(&path, 7).is_none());
+        fs::remove_dir_all(&cache.directory).unwrap();
+    }
+
+    #[test]
+    fn validated_quarters_have_their_own_cache_key() {
+        let cache = cache("key");
+        let raw = quarters(&["CG", "CH", "CI"]);
+        let mut dropped = raw.clone();
+        dropped.quarters_vector[0].retain(|stock| stock.stock_id.name.name() != "CH");
+        assert_eq!(cache.key(&raw, "normalised"), cache.key(&raw.clone(), "normalised"));
+        assert_ne!(cache.key(&raw, "normalised"), cache.key(&dropped, "normalised"));
+        assert_ne!(cache.key(&raw, "normalised"), cache.key(&raw, "percentile"));
+        fs::remove_dir_all(&cache.directory).unwrap();
+    }
+
+    #[test]
+    fn a_cache_file_in_a_different_stock_order_is_rebuilt() {
+        let cache = cache("stock-order");
+        let path = cache.directory.join("normalised.bin");
+        let raw = quarters(&["CD", "CE", "CF"]);
+        write_quarters(&path, 7, &quarters(&["CE", "CD", "CF"])).unwrap();
+        assert!(!read_quarters::<f64>(&path, 7).unwrap().has_same_stocks(&raw));
+        let transformed = cache.load_transformed(&path, 7, &raw, || Ok(raw.clone())).unwrap();
+        assert_eq!(names(&transformed), vec!["CD", "CE", "CF"]);
+        assert!(read_quarters::<f64>(&path, 7).unwrap().has_same_stocks(&raw));
+        fs::remove_dir_all(&cache.directory).unwrap();
+    }
+}
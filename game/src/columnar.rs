@@ -0,0 +1,346 @@
+use std::fmt;
+
+use crate::data_trait::DataTrait;
+use crate::data_record::{StockID, TimeID};
+use crate::player::Player;
+use crate::quarter::Quarter;
+use crate::quarters::Quarters;
+
+/// A fixed length set of bits, stored 64 to a word. Bits past the length are always zero.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize
+}
+
+/// One field of a ColumnarQuarter. Missing values are stored as zero, and marked as invalid in
+/// the validity Bitmap.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Column<T: DataTrait> {
+    pub values: Vec<T>,
+    pub validity: Bitmap
+}
+
+/// A Quarter stored by field rather than by stock: one contiguous Column per field, in the same
+/// stock order as the Quarter it was built from.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ColumnarQuarter<T: DataTrait> {
+    pub columns: Vec<Column<T>>,
+    pub stock_ids: Vec<StockID>,
+    pub time_id: TimeID
+}
+
+/// A Quarters set stored as ColumnarQuarters.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ColumnarQuarters<T: DataTrait> {
+    pub quarters_vector: Vec<ColumnarQuarter<T>>
+}
+
+impl fmt::Display for Bitmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bitmap[len: {}, ones: {}]", self.len, self.count_ones())
+    }
+}
+
+impl<T: DataTrait> fmt::Display for ColumnarQuarter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ColumnarQuarter[columns: {}, len: {}, time_id: {}]", self.columns.len(), self.len(), self.time_id)
+    }
+}
+
+impl Bitmap {
+    /// Creates a Bitmap with every bit set to the same value.
+    ///
+    /// # Arguments
+    /// * `len` - The number of bits.
+    /// * `value` - The value of every bit.
+    pub fn new(len: usize, value: bool) -> Bitmap {
+        let mut bitmap = Bitmap {
+            words: Vec::new(),
+            len: 0
+        };
+        bitmap.fill(len, value);
+        bitmap
+    }
+    /// Creates a Bitmap from a slice of bools.
+    ///
+    /// # Arguments
+    /// * `bools` - The value of each bit.
+    pub fn from_bools(bools: &[bool]) -> Bitmap {
+        let mut bitmap = Bitmap::new(bools.len(), false);
+        for (i, value) in bools.iter().enumerate() {
+            bitmap.set(i, *value);
+        }
+        bitmap
+    }
+    /// Resets the Bitmap to a new length with every bit set to the same value, reusing its
+    /// allocation.
+    ///
+    /// # Arguments
+    /// * `len` - The number of bits.
+    /// * `value` - The value of every bit.
+    pub fn fill(&mut self, len: usize, value: bool) {
+        self.words.clear();
+        self.words.resize(len.div_ceil(64), if value {u64::MAX} else {0});
+        self.len = len;
+        if value && !len.is_multiple_of(64) {
+            *self.words.last_mut().unwrap() = (1 << (len % 64)) - 1;
+        }
+    }
+    /// Makes this Bitmap a copy of another, reusing its allocation.
+    ///
+    /// # Arguments
+    /// * `other` - The Bitmap to copy.
+    pub fn copy_from(&mut self, other: &Bitmap) {
+        self.words.clear();
+        self.words.extend_from_slice(&other.words);
+        self.len = other.len;
+    }
+    /// Returns the number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Returns true if the Bitmap has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Gets the value of a bit.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the bit.
+    pub fn get(&self, index: usize) -> bool {
+        (self.words[index / 64] >> (index % 64)) & 1 == 1
+    }
+    /// Sets the value of a bit.
+    ///
+    /// # Arguments
+    /// * `index` - The index of the bit.
+    /// * `value` - The new value of the bit.
+    pub fn set(&mut self, index: usize, value: bool) {
+        if value {
+            self.words[index / 64] |= 1 << (index % 64);
+        } else {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
+    /// Returns the words of the Bitmap, lowest bits first.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().fold(0, |acc, word| acc + word.count_ones() as usize)
+    }
+    /// Returns an iterator over the indicies of the set bits, in ascending order.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(w, word)| {
+            let mut remaining = *word;
+            std::iter::from_fn(move || {
+                if remaining == 0 {
+                    None
+                } else {
+                    let bit = remaining.trailing_zeros() as usize;
+                    remaining &= remaining - 1;
+                    Some(w * 64 + bit)
+                }
+            })
+        })
+    }
+    /// Clears every bit whose row fails a filter, 64 rows at a time.
+    ///
+    /// # Arguments
+    /// * `values` - The column being filtered, one value per bit.
+    /// * `validity` - Which values exist. Rows with missing values always pass.
+    /// * `passes` - The filter applied to each existing value.
+    pub fn filter<T: Copy, F: Fn(T) -> bool>(&mut self, values: &[T], validity: &Bitmap, passes: F) {
+        for (w, (word, valid_word)) in self.words.iter_mut().zip(validity.words.iter()).enumerate() {
+            if *word == 0 {
+                continue;
+            }
+            let mut passed = !*valid_word;
+            for (bit, value) in values[(w * 64)..((w * 64 + 64).min(values.len()))].iter().enumerate() {
+                passed |= (passes(*value) as u64) << bit;
+            }
+            *word &= passed;
+        }
+    }
+}
+
+impl<T: DataTrait> ColumnarQuarter<T> {
+    /// Creates a ColumnarQuarter from a Quarter.
+    ///
+    /// # Arguments
+    /// * `quarter` - The Quarter to convert.
+    /// * `num_fields` - The number of fields in each DataRecord of the Quarter.
+    pub fn from_quarter(quarter: &Quarter<T>, num_fields: usize) -> ColumnarQuarter<T> {
+        let columns = (0..num_fields).map(|j| {
+            let mut validity = Bitmap::new(quarter.len(), false);
+            let values = quarter.iter().enumerate().map(|(i, stock)| {
+                match stock.get(j) {
+                    Some(value) => {
+                        validity.set(i, true);
+                        value
+                    },
+                    None => T::zero()
+                }
+            }).collect();
+            Column {
+                values: values,
+                validity: validity
+            }
+        }).collect();
+        ColumnarQuarter {
+            columns: columns,
+            stock_ids: quarter.iter().map(|stock| stock.stock_id).collect(),
+            time_id: quarter.time_id
+        }
+    }
+    /// Returns the number of stocks in the ColumnarQuarter.
+    pub fn len(&self) -> usize {
+        self.stock_ids.len()
+    }
+    /// Returns true if the ColumnarQuarter holds no stocks.
+    pub fn is_empty(&self) -> bool {
+        self.stock_ids.is_empty()
+    }
+    /// Buys for a Player every eligible stock that satisfies its strategy, and sells every
+    /// held stock that isn't continued into this quarter.
+    ///
+    /// # Arguments
+    /// * `float_quarter` - The raw data of this quarter, in the same stock order, used for prices.
+    /// * `player` - A Player struct.
+    /// * `eligible` - Which stocks are in the Universe, and can be bought.
+    /// * `index` - The index of the field used to value trades.
+    /// * `iteration` - The number of the current iteration.
+    ///
+    /// # Remarks
+    /// The strategy is applied as column filters to the Player's selection Bitmap, so nothing is
+    /// allocated beyond the growth of the Player's trade lists. Stocks without a value of the
+    /// index field aren't bought, and held stocks without one are sold like delisted stocks.
+    pub fn select_for_player(&self, float_quarter: &Quarter<f64>, player: &mut Player<T>, eligible: &Bitmap, index: usize, iteration: usize) {
+        // Buy from quarter
+        player.selection.copy_from(eligible);
+        player.strategy.select(self, &mut player.selection);
+        for i in player.selection.iter_ones() {
+            let stock_id = self.stock_ids[i];
+            if stock_id.iteration == iteration {
+                // A stock without a price can't be bought
+                if let Some(buy_price) = float_quarter.get(i).and_then(|stock| stock.get(index)) {
+                    player.stocks_purchased.push((buy_price, stock_id));
+                }
+            }
+        }
+        // Sell discontinuous stocks, create a list of what to sell
+        let mut indicies_to_bin: Vec<(usize, StockID)> = Vec::new();
+        for (i, (_, stock_id)) in player.stocks_purchased.iter().enumerate() {  // THIS ITER IS ORDERED BY DEFINITION
+            if stock_id.time_id.is_date(&self.time_id) {
+                indicies_to_bin.retain(|(_, bin_stock_id)| !(stock_id.is_name(bin_stock_id) && bin_stock_id.is_immediate_previous_of(stock_id)));
+            } else {
+                indicies_to_bin.push((i, *stock_id));  // throw away everything not in this quarter
+            }
+        }
+        // Fully constructed bin list, construct payoff and chuck
+        for (i, stock_id) in indicies_to_bin.iter().rev() {
+            let buy_price = player.stocks_purchased[*i].0;
+            let sell_price;
+            match float_quarter.find_by_ticker(&stock_id.name).and_then(|stock| stock.get(index)) {
+                Some(current_value) => {
+                    sell_price = current_value;
+                    player.spend += buy_price;
+                    player.spend_return += sell_price;
+                },
+                None => {   // stock no longer exists or has no price, you bought and couldn't sell
+                    sell_price = buy_price;
+                    player.spend += buy_price;
+                }
+            }
+            let (buy_price, stock_removed) = player.stocks_purchased.remove(*i);
+            player.stocks_sold.push((buy_price, sell_price, stock_removed));
+        }
+    }
+}
+
+impl<T: DataTrait> ColumnarQuarters<T> {
+    /// Creates a ColumnarQuarters set from a Quarters set.
+    ///
+    /// # Arguments
+    /// * `quarters` - The Quarters set to convert.
+    pub fn from_quarters(quarters: &Quarters<T>) -> ColumnarQuarters<T> {
+        ColumnarQuarters {
+            quarters_vector: quarters.iter().map(|quarter| ColumnarQuarter::from_quarter(quarter, quarters.field_names.len())).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::DataRecord;
+    use crate::screener::{Rule, Screener};
+    use crate::ticker::Ticker;
+
+    fn quarter(period: &str, prices: &[(&str, Option<f64>)]) -> Quarter<f64> {
+        let time_id = TimeID::from_name(period).unwrap();
+        Quarter::new(prices.iter().map(|(name, price)| DataRecord {
+            record: vec![*price],
+            stock_id: StockID {
+                name: Ticker::intern(name),
+                time_id: time_id,
+                iteration: 0
+            }
+        }).collect(), time_id)
+    }
+
+    #[test]
+    fn bits_past_the_length_are_never_set() {
+        let bitmap = Bitmap::new(70, true);
+        assert_eq!(bitmap.count_ones(), 70);
+        assert_eq!(bitmap.words(), &[u64::MAX, (1 << 6) - 1]);
+        assert_eq!(bitmap.iter_ones().last(), Some(69));
+        let sparse = Bitmap::from_bools(&[false, true, false, true]);
+        assert_eq!(sparse.iter_ones().collect::<Vec<usize>>(), vec![1, 3]);
+        assert!(!sparse.get(2));
+    }
+
+    #[test]
+    fn filters_clear_failing_rows_and_keep_missing_values() {
+        let values = (0..70).collect::<Vec<usize>>();
+        let mut validity = Bitmap::new(70, true);
+        validity.set(1, false);
+        validity.set(65, false);
+        let mut selection = Bitmap::new(70, true);
+        selection.filter(&values, &validity, |value| value >= 64);
+        assert_eq!(selection.iter_ones().collect::<Vec<usize>>(), vec![1, 64, 65, 66, 67, 68, 69]);
+    }
+
+    #[test]
+    fn screeners_select_by_column() {
+        let quarter = quarter("2010-Q1", &[("PD", Some(1.0)), ("PE", None), ("PF", Some(3.0))]);
+        let mut selection = Bitmap::new(3, true);
+        Screener {
+            screen: vec![(2.0, true, Rule::Gt)]
+        }.select(&ColumnarQuarter::from_quarter(&quarter, 1), &mut selection);
+        assert_eq!(selection.iter_ones().collect::<Vec<usize>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn stocks_without_a_price_are_never_bought_and_are_sold_as_delisted() {
+        let mut player = Player::new_player(Screener {
+            screen: vec![(0.0, false, Rule::Gt)]
+        });
+        let first = quarter("2010-Q1", &[("PA", Some(10.0)), ("PB", None), ("PC", Some(5.0))]);
+        let second = quarter("2010-Q2", &[("PA", None), ("PC", Some(8.0))]);
+        for quarter in [&first, &second] {
+            ColumnarQuarter::from_quarter(quarter, 1).select_for_player(quarter, &mut player, &Bitmap::new(quarter.len(), true), 0, 0);
+        }
+        let bought = player.stocks_purchased.iter().map(|(price, stock_id)| (*price, stock_id.name.name(), stock_id.time_id.period)).collect::<Vec<(f64, &str, i64)>>();
+        assert_eq!(bought, vec![(5.0, "PC", 1), (8.0, "PC", 2)]);
+        let sold = player.stocks_sold.iter().map(|(buy_price, sell_price, stock_id)| (*buy_price, *sell_price, stock_id.name.name())).collect::<Vec<(f64, f64, &str)>>();
+        assert_eq!(sold, vec![(10.0, 10.0, "PA")]);
+        assert_eq!((player.spend, player.spend_return), (10.0, 0.0));
+    }
+}
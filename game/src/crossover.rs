@@ -0,0 +1,126 @@
+use std::fmt;
+
+use crate::data_trait::DataTrait;
+use crate::screener::Screener;
+
+/// How two Screeners are combined into a child.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum Crossover {
+    /// Thresholds are averaged, and each used flag and rule comes from either parent (the
+    /// original crossover).
+    Averaging,
+    /// Fields before a random cut point come from the first parent, the rest from the second.
+    OnePoint,
+    /// Fields between two random cut points come from the second parent, the rest from the first.
+    TwoPoint,
+    /// Each field comes whole from either parent.
+    Uniform,
+    /// Rules used by both parents are kept, and rules used by one are kept half the time.
+    RuleSet
+}
+
+impl fmt::Display for Crossover {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Crossover::Averaging => write!(f, "Averaging"),
+            Crossover::OnePoint => write!(f, "OnePoint"),
+            Crossover::TwoPoint => write!(f, "TwoPoint"),
+            Crossover::Uniform => write!(f, "Uniform"),
+            Crossover::RuleSet => write!(f, "RuleSet")
+        }
+    }
+}
+
+impl Crossover {
+    /// Parse a Crossover from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "average", "one_point", "two_point", "uniform" or "rule_set".
+    pub fn from_name(name: &str) -> Option<Crossover> {
+        match name {
+            "average" => Some(Crossover::Averaging),
+            "one_point" => Some(Crossover::OnePoint),
+            "two_point" => Some(Crossover::TwoPoint),
+            "uniform" => Some(Crossover::Uniform),
+            "rule_set" => Some(Crossover::RuleSet),
+            _ => None
+        }
+    }
+    /// Crosses two Screeners.
+    ///
+    /// # Arguments
+    /// * `first` - The first parent.
+    /// * `second` - The second parent.
+    /// * `percentile_gap` - The percentile gap that averaged thresholds are rounded to.
+    pub fn cross<T: DataTrait>(&self, first: &Screener<T>, second: &Screener<T>, percentile_gap: usize) -> Screener<T> {
+        match self {
+            Crossover::Averaging => first.dumb_crossover(second, percentile_gap),
+            Crossover::OnePoint => first.one_point_crossover(second),
+            Crossover::TwoPoint => first.two_point_crossover(second),
+            Crossover::Uniform => first.uniform_crossover(second),
+            Crossover::RuleSet => first.rule_set_crossover(second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screener::Rule;
+
+    /// Eight fields, with thresholds of `scale` times 1 to 8.
+    fn parent(scale: f64, used: bool, rule: Rule) -> Screener<f64> {
+        Screener {
+            screen: (1..=8).map(|i| (scale * (i as f64), used, rule.clone())).collect()
+        }
+    }
+
+    /// Which parent each field of a child came from whole, true for the first.
+    fn sources(child: &Screener<f64>) -> Vec<bool> {
+        child.iter().enumerate().map(|(i, (threshold, used, rule))| {
+            let first = (*threshold == (i + 1) as f64) && *used && matches!(rule, Rule::Lt);
+            let second = (*threshold == 10.0 * (i + 1) as f64) && !*used && matches!(rule, Rule::Gt);
+            assert!(first || second, "field {} was mixed", i);
+            first
+        }).collect()
+    }
+
+    /// The number of times consecutive fields come from different parents.
+    fn switches(sources: &[bool]) -> usize {
+        sources.windows(2).filter(|pair| pair[0] != pair[1]).count()
+    }
+
+    #[test]
+    fn point_crossovers_cut_the_parents() {
+        let (first, second) = (parent(1.0, true, Rule::Lt), parent(10.0, false, Rule::Gt));
+        for _ in 0..50 {
+            let one_point = sources(&Crossover::OnePoint.cross(&first, &second, 1));
+            assert!((switches(&one_point) <= 1) && (one_point[0] || !one_point[7]));
+            let two_point = sources(&Crossover::TwoPoint.cross(&first, &second, 1));
+            assert!((switches(&two_point) <= 2) && (two_point[0] == two_point[7] || switches(&two_point) <= 1));
+        }
+    }
+
+    #[test]
+    fn uniform_crossover_takes_whole_fields() {
+        let (first, second) = (parent(1.0, true, Rule::Lt), parent(10.0, false, Rule::Gt));
+        for _ in 0..50 {
+            sources(&Crossover::Uniform.cross(&first, &second, 1));
+        }
+    }
+
+    #[test]
+    fn averaging_crossover_averages_thresholds() {
+        let child = Crossover::Averaging.cross(&parent(1.0, true, Rule::Lt), &parent(3.0, true, Rule::Lt), 1);
+        assert_eq!(child.iter().map(|(threshold, _, _)| *threshold).collect::<Vec<f64>>(), (1..=8).map(|i| 2.0 * (i as f64)).collect::<Vec<f64>>());
+    }
+
+    #[test]
+    fn rule_set_crossover_keeps_shared_rules() {
+        let both = Crossover::RuleSet.cross(&parent(1.0, true, Rule::Lt), &parent(2.0, true, Rule::Gt), 1);
+        assert!(both.iter().all(|(_, used, _)| *used));
+        let neither = Crossover::RuleSet.cross(&parent(1.0, false, Rule::Lt), &parent(2.0, false, Rule::Gt), 1);
+        assert!(neither.iter().all(|(_, used, _)| !*used));
+    }
+}
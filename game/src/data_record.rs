@@ -3,6 +3,7 @@ use std::{fmt, slice::Iter};
 use crate::data_trait::DataTrait;
 use crate::player::Player;
 use crate::screener::Rule;
+use crate::ticker::Ticker;
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -12,29 +13,50 @@ pub struct DataRecord<T: DataTrait> {
 }
 
 #[derive(Debug)]
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct StockID {
-    pub name: String,
+    pub name: Ticker,
     pub time_id: TimeID,
     pub iteration: usize
 }
 
+/// How often a stock reports, and so how long each period of the data is.
 #[derive(Debug)]
-#[derive(Clone)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Monthly,
+    Quarterly,
+    Annual
+}
+
+/// A period of the data: a month, a quarter or a year, numbered from 1 within its year.
+#[derive(Debug)]
+#[derive(Clone, Copy)]
 pub struct TimeID {
     pub year: i64,
-    pub quarter: i64
+    pub period: i64,
+    pub frequency: Frequency
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Frequency::Monthly => write!(f, "Frequency[Monthly]"),
+            Frequency::Quarterly => write!(f, "Frequency[Quarterly]"),
+            Frequency::Annual => write!(f, "Frequency[Annual]")
+        }
+    }
 }
 
 impl fmt::Display for TimeID {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "TimeID[year: {}, quarter: {}]", self.year, self.quarter)
+        write!(f, "TimeID[year: {}, period: {}, frequency: {}]", self.year, self.period, self.frequency)
     }
 }
 
 impl fmt::Display for StockID {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "StockID[name: {:?}, time_id: {}]", self.name, self.time_id)
+        write!(f, "StockID[name: {:?}, time_id: {}]", self.name.name(), self.time_id)
     }
 }
 
@@ -44,33 +66,121 @@ impl<T: DataTrait> fmt::Display for DataRecord<T> {
     }
 }
 
+impl Frequency {
+    /// Returns the Frequency with a name: "monthly", "quarterly" or "annual".
+    pub fn from_name(name: &str) -> Option<Frequency> {
+        match name {
+            "monthly" => Some(Frequency::Monthly),
+            "quarterly" => Some(Frequency::Quarterly),
+            "annual" => Some(Frequency::Annual),
+            _ => None
+        }
+    }
+    /// Returns the number of periods in a year.
+    pub fn periods_per_year(&self) -> i64 {
+        match self {
+            Frequency::Monthly => 12,
+            Frequency::Quarterly => 4,
+            Frequency::Annual => 1
+        }
+    }
+    /// Returns the number of months in a period.
+    pub fn months_per_period(&self) -> i64 {
+        12 / self.periods_per_year()
+    }
+    /// Parses the period within a year, written like "3", or with the letter of the frequency
+    /// like "Q3" or "M03". Every annual period is 1, whatever is written. Returns None if it isn't
+    /// a period of this frequency.
+    pub fn parse_period(&self, period: &str) -> Option<i64> {
+        let letter = match self {
+            Frequency::Monthly => 'M',
+            Frequency::Quarterly => 'Q',
+            Frequency::Annual => return Some(1)
+        };
+        match period.trim().trim_start_matches(|c: char| c.to_ascii_uppercase() == letter).parse::<i64>() {
+            Ok(period) if (1..=self.periods_per_year()).contains(&period) => Some(period),
+            _ => None
+        }
+    }
+}
+
 impl TimeID {
     pub fn is_date(&self, time_id: &TimeID) -> bool {
-        (self.quarter == time_id.quarter) & (self.year == time_id.year)
+        (self.period == time_id.period) & (self.year == time_id.year) & (self.frequency == time_id.frequency)
     }
 
     pub fn is_immediate_previous_of(&self, time_id: &TimeID) -> bool {
-        if time_id.quarter != 1 {
-            self.quarter + 1 == time_id.quarter
-        } else {
-            (self.year + 1 == time_id.year) && (self.quarter == 4)
-        }
+        (self.frequency == time_id.frequency) && (self.index() + 1 == time_id.index())
     }
-
+    /// Returns true if this period starts at or after the start of time_id.
     pub fn after(&self, time_id: &TimeID) -> bool {
-        if self.year == time_id.year {
-            self.quarter >= time_id.quarter
-        } else {
-            self.year >= time_id.year
-        }
+        self.first_month() >= time_id.first_month()
     }
     /// Assumes that time_id.after(self) | time_id.is_date(self) is true.
     pub fn years_until(&self, time_id: &TimeID) -> f64 {
-        ((4 * (time_id.year - self.year) + (time_id.quarter - self.quarter)) as f64) / 4.0
+        ((time_id.first_month() - self.first_month()) as f64) / 12.0
+    }
+    /// Returns the number of periods since the first period of year zero, so consecutive
+    /// periods have consecutive indices.
+    pub fn index(&self) -> i64 {
+        self.year * self.frequency.periods_per_year() + (self.period - 1)
+    }
+    /// The inverse of index.
+    pub fn from_index(index: i64, frequency: Frequency) -> TimeID {
+        TimeID {
+            year: index.div_euclid(frequency.periods_per_year()),
+            period: index.rem_euclid(frequency.periods_per_year()) + 1,
+            frequency: frequency
+        }
+    }
+    /// Returns the period of a frequency that holds a month.
+    ///
+    /// # Arguments
+    /// * `month` - The number of months since the start of year zero.
+    /// * `frequency` - The frequency of the period.
+    pub fn from_month(month: i64, frequency: Frequency) -> TimeID {
+        TimeID {
+            year: month.div_euclid(12),
+            period: month.rem_euclid(12) / frequency.months_per_period() + 1,
+            frequency: frequency
+        }
+    }
+    /// Returns the number of months from the start of year zero to the first month of the period.
+    pub fn first_month(&self) -> i64 {
+        self.year * 12 + (self.period - 1) * self.frequency.months_per_period()
+    }
+    /// Returns the number of months from the start of year zero to the last month of the period.
+    pub fn last_month(&self) -> i64 {
+        self.first_month() + self.frequency.months_per_period() - 1
+    }
+    /// Parses a period written like "2010" for a year, "2010-M03" for a month, or "2010-1",
+    /// "2010Q1" or "2010-Q1" for a quarter. Returns None if it isn't one.
+    pub fn from_name(name: &str) -> Option<TimeID> {
+        let name = name.trim();
+        let split = match name.find(|c: char| !c.is_ascii_digit()) {
+            Some(split) => split,
+            None => return Some(TimeID {
+                year: name.parse::<i64>().ok()?,
+                period: 1,
+                frequency: Frequency::Annual
+            })
+        };
+        let year = name[..split].parse::<i64>().ok()?;
+        let period = name[split..].trim_start_matches('-');
+        let frequency = if period.starts_with(['M', 'm']) {Frequency::Monthly} else {Frequency::Quarterly};
+        Some(TimeID {
+            year: year,
+            period: frequency.parse_period(period)?,
+            frequency: frequency
+        })
     }
 
     pub fn to_string(&self) -> String {
-        format!("{}-{}", self.year, self.quarter)
+        match self.frequency {
+            Frequency::Monthly => format!("{}-M{:02}", self.year, self.period),
+            Frequency::Quarterly => format!("{}-{}", self.year, self.period),
+            Frequency::Annual => format!("{}", self.year)
+        }
     }
 }
 
@@ -149,3 +259,98 @@ impl<T: DataTrait> DataRecord<T> {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(year: i64, period: i64, frequency: Frequency) -> TimeID {
+        TimeID {
+            year: year,
+            period: period,
+            frequency: frequency
+        }
+    }
+
+    fn assert_parses(name: &str, expected: TimeID) {
+        match TimeID::from_name(name) {
+            Some(time_id) => assert!(time_id.is_date(&expected), "{:?} parsed as {}, not {}", name, time_id, expected),
+            None => panic!("{:?} didn't parse", name)
+        }
+    }
+
+    #[test]
+    fn periods_parse_with_their_frequency() {
+        assert_parses("2010", time(2010, 1, Frequency::Annual));
+        assert_parses("2010-M03", time(2010, 3, Frequency::Monthly));
+        assert_parses("2010-m12", time(2010, 12, Frequency::Monthly));
+        assert_parses("2010Q1", time(2010, 1, Frequency::Quarterly));
+        assert_parses("2010-Q4", time(2010, 4, Frequency::Quarterly));
+        assert_parses(" 2010-2 ", time(2010, 2, Frequency::Quarterly));
+    }
+
+    #[test]
+    fn invalid_periods_are_rejected() {
+        for name in ["2010-13", "2010-5", "2010-Q0", "2010-M00", "2010-M13", "2010-", "2010-Qx", "", "Q1", "twenty"].iter() {
+            assert!(TimeID::from_name(name).is_none(), "{:?} shouldn't parse", name);
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for frequency in [Frequency::Monthly, Frequency::Quarterly, Frequency::Annual].iter() {
+            for period in 1..=frequency.periods_per_year() {
+                let time_id = time(1999, period, *frequency);
+                assert_parses(&time_id.to_string(), time_id);
+            }
+        }
+        assert_eq!(time(2010, 3, Frequency::Monthly).to_string(), "2010-M03");
+        assert_eq!(time(2010, 3, Frequency::Quarterly).to_string(), "2010-3");
+        assert_eq!(time(2010, 1, Frequency::Annual).to_string(), "2010");
+    }
+
+    #[test]
+    fn periods_parse_within_their_frequency() {
+        assert_eq!(Frequency::Monthly.parse_period("M12"), Some(12));
+        assert_eq!(Frequency::Monthly.parse_period("7"), Some(7));
+        assert_eq!(Frequency::Monthly.parse_period("M13"), None);
+        assert_eq!(Frequency::Monthly.parse_period("Q1"), None);
+        assert_eq!(Frequency::Quarterly.parse_period("q2"), Some(2));
+        assert_eq!(Frequency::Quarterly.parse_period("Q5"), None);
+        assert_eq!(Frequency::Quarterly.parse_period("M1"), None);
+        assert_eq!(Frequency::Annual.parse_period(""), Some(1));
+        assert_eq!(Frequency::Annual.parse_period("Q3"), Some(1));
+    }
+
+    #[test]
+    fn indices_round_trip_and_are_consecutive() {
+        for frequency in [Frequency::Monthly, Frequency::Quarterly, Frequency::Annual].iter() {
+            for index in -30..30 {
+                let time_id = TimeID::from_index(index, *frequency);
+                assert_eq!(time_id.index(), index);
+                assert!(time_id.is_immediate_previous_of(&TimeID::from_index(index + 1, *frequency)));
+            }
+        }
+        assert!(TimeID::from_index(-1, Frequency::Quarterly).is_date(&time(-1, 4, Frequency::Quarterly)));
+        assert!(!time(2010, 4, Frequency::Quarterly).is_immediate_previous_of(&time(2011, 1, Frequency::Monthly)));
+    }
+
+    #[test]
+    fn months_map_onto_periods() {
+        assert!(TimeID::from_month(2010 * 12, Frequency::Quarterly).is_date(&time(2010, 1, Frequency::Quarterly)));
+        assert!(TimeID::from_month(2010 * 12 + 11, Frequency::Quarterly).is_date(&time(2010, 4, Frequency::Quarterly)));
+        assert!(TimeID::from_month(2010 * 12 + 11, Frequency::Monthly).is_date(&time(2010, 12, Frequency::Monthly)));
+        assert!(TimeID::from_month(2010 * 12 - 1, Frequency::Annual).is_date(&time(2009, 1, Frequency::Annual)));
+        assert_eq!(time(2010, 2, Frequency::Quarterly).first_month(), 2010 * 12 + 3);
+        assert_eq!(time(2010, 2, Frequency::Quarterly).last_month(), 2010 * 12 + 5);
+        assert_eq!(time(2010, 1, Frequency::Annual).last_month(), 2010 * 12 + 11);
+    }
+
+    #[test]
+    fn years_are_counted_in_months() {
+        assert_eq!(time(2010, 1, Frequency::Quarterly).years_until(&time(2012, 1, Frequency::Quarterly)), 2.0);
+        assert_eq!(time(2010, 1, Frequency::Quarterly).years_until(&time(2010, 3, Frequency::Quarterly)), 0.5);
+        assert_eq!(time(2010, 1, Frequency::Monthly).years_until(&time(2010, 7, Frequency::Monthly)), 0.5);
+        assert_eq!(time(2010, 1, Frequency::Annual).years_until(&time(2015, 1, Frequency::Annual)), 5.0);
+    }
+}
@@ -18,7 +18,7 @@ impl DataTrait for usize {
 }
 impl DataTrait for f64 {
     fn interval(&self, percentage: f64) -> (f64, f64) {
-        let difference = self * (percentage / 100.0);
+        let difference = (self * (percentage / 100.0)).abs();
         (self - difference, self + difference)
     }
     fn round(&self, _percentile_gap: usize) -> f64 {
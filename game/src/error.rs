@@ -0,0 +1,51 @@
+use std::{error, fmt, io, path::PathBuf};
+
+/// Everything that can go wrong loading data, configuring a Game, or saving its results.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or directory couldn't be read or written.
+    Io(PathBuf, io::Error),
+    /// A CSV file couldn't be read or written.
+    Csv(PathBuf, csv::Error),
+    /// The data doesn't have the shape that was expected, like a missing column.
+    Schema(String),
+    /// A value, in the data or typed in, couldn't be parsed.
+    Parse(String),
+    /// The settings are invalid, or don't match the data.
+    Config(String)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(path, why) => write!(f, "I/O error on {}: {}", path.display(), why),
+            Error::Csv(path, why) => write!(f, "CSV error in {}: {}", path.display(), why),
+            Error::Schema(why) => write!(f, "Data error: {}", why),
+            Error::Parse(why) => write!(f, "Parse error: {}", why),
+            Error::Config(why) => write!(f, "Configuration error: {}", why)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(_, why) => Some(why),
+            Error::Csv(_, why) => Some(why),
+            _ => None
+        }
+    }
+}
+
+impl Error {
+    /// The exit code of the binary when it stops with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => 2,
+            Error::Io(_, _) => 3,
+            Error::Csv(_, _) => 4,
+            Error::Schema(_) => 5,
+            Error::Parse(_) => 6
+        }
+    }
+}
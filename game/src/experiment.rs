@@ -0,0 +1,218 @@
+use std::path::PathBuf;
+
+use crate::cache::Cache;
+use crate::data_trait::DataTrait;
+use crate::error::Error;
+use crate::fields::FieldSelection;
+use crate::game::Game;
+use crate::quarters::Quarters;
+use crate::settings::Settings;
+use crate::statistics::StatsLog;
+
+/// Resolves the field settings against the field names of some quarters. Returns an error if a
+/// name is unknown, or a required field has no values.
+pub fn select_fields(settings: &Settings, quarters: &Quarters<f64>) -> Result<FieldSelection, Error> {
+    settings.fields.select(quarters)
+}
+
+/// Opens the cache directory of the settings, relative to the root of its paths. Returns None
+/// if caching is off or the cache can't be opened.
+pub fn open_cache(settings: &Settings) -> Option<Cache> {
+    let directory = settings.cache_directory.as_ref()?;
+    match Cache::new(settings.paths.resolve(directory), &settings.paths.data_directory(), &settings.loading) {
+        Ok(cache) => Some(cache),
+        Err(why) => {
+            println!("Not using the cache: {}", why);
+            None
+        }
+    }
+}
+
+/// Loads the raw quarters, from the cache if there is one and from the data directory of the
+/// settings if not, and prints how many stocks are in each quarter.
+pub fn load_quarters(cache: &Option<Cache>, settings: &Settings, iteration_max: usize) -> Result<Quarters<f64>, Error> {
+    let mut quarters = match cache {
+        Some(cache) => cache.load_quarters(iteration_max)?,
+        None => Quarters::<f64>::new_quarters_from_directory(&settings.paths.data_directory(), &settings.loading, iteration_max)?
+    };
+    if settings.loading.validation.enabled {
+        let value_index = select_fields(settings, &quarters)?.index_of_value;
+        let report = settings.loading.validation.validate(&mut quarters, value_index);
+        for line in report.lines() {
+            println!("{}", line);
+        }
+        println!("{}", report);
+    }
+    for line in quarters.coverage_table() {
+        println!("{}", line);
+    }
+    Ok(quarters)
+}
+
+/// Creates the percentile quarters of some raw quarters, from the cache if there is one.
+/// Returns an error if the gap doesn't evenly divide 100.
+pub fn percentile_quarters(cache: &Option<Cache>, quarters: &Quarters<f64>, gap: usize, settings: &Settings) -> Result<Quarters<usize>, Error> {
+    match cache {
+        Some(cache) => cache.load_percentile_quarters(quarters, gap, &settings.percentile_settings),
+        None => quarters.create_percentile_quarters(gap, &settings.percentile_settings)
+    }
+}
+
+/// Creates the normalised quarters of some raw quarters, from the cache if there is one.
+/// Returns an error if the normalisation of the settings can't be applied.
+pub fn normalised_quarters(cache: &Option<Cache>, quarters: &Quarters<f64>, settings: &Settings) -> Result<Quarters<f64>, Error> {
+    match cache {
+        Some(cache) => cache.load_normalised_quarters(quarters, &settings.normalisation),
+        None => quarters.create_normalised_quarters(&settings.normalisation)
+    }
+}
+
+/// Builds the thread pool that players are evaluated on.
+pub fn thread_pool(settings: &Settings) -> Result<rayon::ThreadPool, Error> {
+    rayon::ThreadPoolBuilder::new().num_threads(settings.threads).build().map_err(|why| Error::Config(format!("couldn't create a thread pool of {} threads: {}", settings.threads, why)))
+}
+
+/// Runs the genetic algorithm for every combination of the settings, saving each run to a file
+/// in the output directory. Stops at the first run that fails.
+pub fn run(settings: &Settings) -> Result<(), Error> {
+    // Percentile gaps only matter in the percentile space, every other space is run once.
+    let percentiles = if settings.normalisation.is_percentile() {settings.percentiles.clone()} else {vec![settings.percentiles[0]]};
+    println!("Running algorithm with {}", settings);
+    println!("This is going to execute the genetic algorithm {:?} times.", settings.runs * settings.population_sizes.len() * settings.generation_maxs.len() * settings.iterations.len() * percentiles.len());
+    let cache = open_cache(settings);
+    settings.niching.check()?;
+    let pool = thread_pool(settings)?;
+    for i in 0..settings.runs {
+        for iteration in &settings.iterations {
+            let quarters = load_quarters(&cache, settings, *iteration)?;
+            let fields = select_fields(settings, &quarters)?;
+            for population_size in &settings.population_sizes {
+                for generation_max in &settings.generation_maxs {
+                    for percentile in &percentiles {
+                        let output = settings.paths.output_file(&format!("output-r{}-{}-g{}-i{}-pop{}.txt", i, settings.normalisation.label(*percentile), *generation_max, *iteration, *population_size));
+                        if settings.normalisation.is_percentile() {
+                            let quarters_actual = percentile_quarters(&cache, &quarters, *percentile, settings)?;
+                            let game = Game::<usize>::new_game_with_quarters(quarters.clone(), quarters_actual, *population_size, *percentile, &fields, &settings.selection, &settings.niching);
+                            pool.install(|| run_game(game, settings, *generation_max, *iteration, *percentile, output))?;
+                        } else {
+                            let quarters_actual = normalised_quarters(&cache, &quarters, settings)?;
+                            let game = Game::<f64>::new_game_with_quarters(quarters.clone(), quarters_actual, *population_size, *percentile, &fields, &settings.selection, &settings.niching);
+                            pool.install(|| run_game(game, settings, *generation_max, *iteration, *percentile, output))?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Configures a Game from the settings, runs it and saves it to output.
+pub fn run_game<T: DataTrait>(mut game: Game<T>, settings: &Settings, generation_max: usize, iteration: usize, percentile: usize, output: PathBuf) -> Result<(), Error> {
+    apply_universe(&mut game, settings)?;
+    game.set_crossover(&settings.crossover);
+    game.set_mutation(&settings.mutation);
+    game.set_hall_of_fame(settings.hall_of_fame);
+    game.set_fitness_cache(settings.fitness_cache);
+    game.set_stopping(&settings.stopping);
+    match StatsLog::create(&StatsLog::path_for_output(&output)) {
+        Ok(stats_log) => game.add_observer(Box::new(stats_log)),
+        Err(why) => println!("Couldn't write statistics: {}", why)
+    }
+    if settings.islands.count > 1 {
+        game.set_islands(&settings.islands);
+    }
+    game.run(generation_max, iteration, percentile, &output)
+}
+
+/// Evaluates a single Screener, read from screener_string or from the input file of the
+/// settings, and prints how it did.
+pub fn test(settings: &Settings, screener_string: Option<String>) -> Result<(), Error> {
+    println!("Running test with lambda=1, gen_max=N/A, iter=1, percentiles=[{:?}], normalisation={}, string={:?}, selection={}, niching={}", settings.percentiles[0], settings.normalisation, screener_string, settings.selection, settings.niching);
+    let cache = open_cache(settings);
+    settings.niching.check()?;
+    let pool = thread_pool(settings)?;
+    let read_quarters = load_quarters(&cache, settings, 1)?;
+    let fields = select_fields(settings, &read_quarters)?;
+
+    if settings.normalisation.is_percentile() {
+        let quarters_actual = percentile_quarters(&cache, &read_quarters, settings.percentiles[0], settings)?;
+        let game = Game::<usize>::new_game_with_quarters(read_quarters, quarters_actual, 1, settings.percentiles[0], &fields, &settings.selection, &settings.niching);
+        pool.install(|| test_game(game, settings, screener_string))
+    } else {
+        let quarters_actual = normalised_quarters(&cache, &read_quarters, settings)?;
+        let game = Game::<f64>::new_game_with_quarters(read_quarters, quarters_actual, 1, settings.percentiles[0], &fields, &settings.selection, &settings.niching);
+        pool.install(|| test_game(game, settings, screener_string))
+    }
+}
+
+fn test_game<T: DataTrait>(mut game: Game<T>, settings: &Settings, screener_string: Option<String>) -> Result<(), Error> {
+    apply_universe(&mut game, settings)?;
+    match screener_string {
+        Some(screener_string) => game.read_string(screener_string, false)?,
+        None => game.read_file(&settings.paths.input_file())?
+    }
+    game.perform_analytical_final_run(0);
+    game.print_best();
+    Ok(())
+}
+
+/// Restricts a Game to the universe of the settings, unless it's the default universe.
+pub fn apply_universe<T: DataTrait>(game: &mut Game<T>, settings: &Settings) -> Result<(), Error> {
+    if !settings.universe.is_default() {
+        game.set_universe(&settings.universe)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Settings for one short run over 20 stocks of 8 quarters, written under root.
+    fn settings(root: &std::path::Path) -> Settings {
+        let data = root.join("data");
+        fs::create_dir_all(&data).unwrap();
+        for stock in 0..20 {
+            let rows = (0..8).map(|quarter| format!("{},{},Q{},{}\n", 10 + (stock * 7 + quarter * 3) % 13, (stock * 5 + quarter) % 11, quarter % 4 + 1, 2010 + quarter / 4)).collect::<String>();
+            fs::write(data.join(format!("STCK{}_unite_trim.csv", stock)), format!("adj_price,pe,period,year\n{}", rows)).unwrap();
+        }
+        let mut settings = Settings::new_default();
+        settings.paths.root = root.to_path_buf();
+        settings.paths.data = "data".into();
+        settings.paths.output = "output".into();
+        settings.cache_directory = None;
+        settings.threads = 1;
+        settings.runs = 1;
+        settings.population_sizes = vec![10];
+        settings.generation_maxs = vec![2];
+        settings.iterations = vec![1];
+        settings
+    }
+
+    #[test]
+    fn a_run_writes_its_output_and_statistics() {
+        let root = std::env::temp_dir().join(format!("game-experiment-test-{}", std::process::id()));
+        let settings = settings(&root);
+        let result = run(&settings);
+        let output = settings.paths.output_file("output-r0-perc10-g2-i1-pop10.txt");
+        let written = (output.is_file(), StatsLog::path_for_output(&output).is_file());
+        let tested = test(&settings, Some("[(pe, Lt, 50)]".to_string()));
+        fs::remove_dir_all(&root).unwrap();
+        result.unwrap();
+        tested.unwrap();
+        assert_eq!(written, (true, true));
+    }
+
+    #[test]
+    fn bad_settings_fail_before_running() {
+        let mut settings = Settings::new_default();
+        settings.cache_directory = None;
+        settings.niching.sigma = 0.0;
+        assert!(matches!(run(&settings), Err(Error::Config(_))));
+        settings.niching.sigma = 0.5;
+        settings.paths.root = std::env::temp_dir().join(format!("game-experiment-missing-{}", std::process::id()));
+        assert!(run(&settings).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use crate::error::Error;
+use crate::quarters::Quarters;
+use crate::validation::QUALITY_FIELD;
+
+/// The price columns that are banned from Screeners unless configured otherwise.
+pub static DEFAULT_BANNED_FIELDS: [&str; 11] = ["adj_close", "adj_factor", "adj_high", "adj_low", "adj_open", "adj_volume", "close", "high", "low", "open", "volume"];
+
+/// Which fields the Screeners may use, must use, and which field values the trades, by name.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct FieldSettings {
+    pub allowed: Option<Vec<String>>,
+    pub banned: Option<Vec<String>>,
+    pub required: Vec<String>,
+    pub value_field: Option<String>
+}
+
+/// A FieldSettings resolved against the field names of a Quarters set.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct FieldSelection {
+    pub banned_indicies: Vec<usize>,
+    pub required_indicies: Vec<usize>,
+    pub index_of_value: usize
+}
+
+impl fmt::Display for FieldSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FieldSettings[allowed: {:?}, banned: {:?}, required: {:?}, value_field: {:?}]", self.allowed, self.banned, self.required, self.value_field)
+    }
+}
+
+impl fmt::Display for FieldSelection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FieldSelection[banned_indicies: {:?}, required_indicies: {:?}, index_of_value: {}]", self.banned_indicies, self.required_indicies, self.index_of_value)
+    }
+}
+
+impl FieldSettings {
+    /// Creates the default FieldSettings: the DEFAULT_BANNED_FIELDS are banned, nothing is
+    /// required, and trades are valued with the first field.
+    pub fn new_default() -> FieldSettings {
+        FieldSettings {
+            allowed: None,
+            banned: None,
+            required: Vec::new(),
+            value_field: None
+        }
+    }
+    /// Resolve the named fields into indicies of the field names of some Quarters.
+    ///
+    /// # Arguments
+    /// * `quarters` - The raw Quarters set that will be screened.
+    ///
+    /// # Remarks
+    /// Every configured name must exist in the field names, and a required field can't also be
+    /// banned, or have no values in the Quarters, since a Screener could never use it. The DEFAULT_BANNED_FIELDS are only banned where they exist. If `allowed` is set,
+    /// every field not in it is banned as well. The QUALITY_FIELD added by validation is always
+    /// banned, so the Screeners never trade on the quality of the data.
+    pub fn select(&self, quarters: &Quarters<f64>) -> Result<FieldSelection, Error> {
+        let field_names = &quarters.field_names;
+        let find = |name: &String| -> Result<usize, Error> {
+            match field_names.iter().position(|field_name| field_name == name) {
+                Some(index) => Ok(index),
+                None => Err(Error::Config(format!("Field {:?} doesn't exist in the data.", name)))
+            }
+        };
+        let mut banned_indicies = match &self.banned {
+            Some(banned) => banned.iter().map(find).collect::<Result<Vec<usize>, Error>>()?,
+            None => field_names.iter().enumerate().filter_map(|(i, field_name)| {
+                if DEFAULT_BANNED_FIELDS.contains(&&field_name[0..]) {Some(i)} else {None}
+            }).collect()
+        };
+        if let Some(i) = field_names.iter().position(|field_name| field_name == QUALITY_FIELD) {
+            if !banned_indicies.contains(&i) {
+                banned_indicies.push(i);
+            }
+        }
+        if let Some(allowed) = &self.allowed {
+            let allowed_indicies = allowed.iter().map(find).collect::<Result<Vec<usize>, Error>>()?;
+            for i in 0..field_names.len() {
+                if !allowed_indicies.contains(&i) & !banned_indicies.contains(&i) {
+                    banned_indicies.push(i);
+                }
+            }
+            banned_indicies.sort();
+        }
+        let required_indicies = self.required.iter().map(find).collect::<Result<Vec<usize>, Error>>()?;
+        for i in &required_indicies {
+            if banned_indicies.contains(i) {
+                return Err(Error::Config(format!("Field {:?} is both required and banned.", field_names[*i])));
+            }
+            if !quarters.is_observed(*i) {
+                return Err(Error::Config(format!("Field {:?} is required but has no values in the data.", field_names[*i])));
+            }
+        }
+        let index_of_value = match &self.value_field {
+            Some(name) => find(name)?,
+            None => 0
+        };
+        Ok(FieldSelection {
+            banned_indicies: banned_indicies,
+            required_indicies: required_indicies,
+            index_of_value: index_of_value
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::{DataRecord, StockID, TimeID};
+    use crate::quarter::Quarter;
+    use crate::ticker::Ticker;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// Quarters of the given fields with a single record, holding the given values.
+    fn quarters(field_names: &[&str], values: Vec<Option<f64>>) -> Quarters<f64> {
+        let time_id = TimeID::from_name("2010-Q1").unwrap();
+        let record = DataRecord {
+            record: values,
+            stock_id: StockID {
+                name: Ticker::intern("FA"),
+                time_id: time_id,
+                iteration: 0
+            }
+        };
+        Quarters {
+            field_names: names(field_names),
+            quarters_vector: vec![Quarter::new(vec![record], time_id)],
+            starting_time: time_id,
+            ending_time: time_id
+        }
+    }
+
+    #[test]
+    fn the_quality_field_is_always_banned() {
+        let field_names = quarters(&["adj_price", "close", "pe", QUALITY_FIELD], vec![Some(1.0); 4]);
+        let mut settings = FieldSettings::new_default();
+        assert_eq!(settings.select(&field_names).unwrap().banned_indicies, vec![1, 3]);
+        settings.banned = Some(names(&["pe"]));
+        assert_eq!(settings.select(&field_names).unwrap().banned_indicies, vec![2, 3]);
+        settings.banned = None;
+        settings.allowed = Some(names(&["pe", QUALITY_FIELD]));
+        assert!(settings.select(&field_names).unwrap().banned_indicies.contains(&3));
+        settings.allowed = None;
+        settings.required = names(&[QUALITY_FIELD]);
+        assert!(settings.select(&field_names).is_err());
+    }
+
+    #[test]
+    fn a_required_field_must_have_values() {
+        let field_names = quarters(&["adj_price", "pe", "roe"], vec![Some(1.0), Some(2.0), None]);
+        let mut settings = FieldSettings::new_default();
+        settings.required = names(&["pe"]);
+        assert_eq!(settings.select(&field_names).unwrap().required_indicies, vec![1]);
+        settings.required = names(&["roe"]);
+        assert!(matches!(settings.select(&field_names), Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,184 @@
+use std::{fmt, collections::HashMap};
+
+use crate::data_record::StockID;
+use crate::data_trait::DataTrait;
+use crate::player::Player;
+use crate::screener::ScreenerKey;
+
+/// Everything a Player records while being run through the test data.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Backtest {
+    pub spend: f64,
+    pub spend_return: f64,
+    pub stocks_sold: Vec<(f64, f64, StockID)>,
+    pub stocks_purchased: Vec<(f64, StockID)>
+}
+
+/// Memoised Backtests, keyed on the used portion of a Screener and the iteration it was run on.
+///
+/// # Remarks
+/// At most capacity Backtests are kept past the end of a generation. The least recently used are
+/// forgotten first, where a Backtest is used in the generation it's stored or looked up in.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct FitnessCache {
+    /// Each Backtest, with the last generation it was used in.
+    results: HashMap<(ScreenerKey, usize), (Backtest, usize)>,
+    capacity: usize,
+    generation: usize,
+    /// The hits and lookups since the last generation was recorded.
+    pending: (usize, usize),
+    pub generation_hits: usize,
+    pub generation_lookups: usize,
+    pub total_hits: usize,
+    pub total_lookups: usize
+}
+
+impl fmt::Display for FitnessCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FitnessCache[entries: {} of {}, generation: {} of {} hit ({:.1}%), total: {} of {} hit ({:.1}%)]", self.results.len(), self.capacity, self.generation_hits, self.generation_lookups, self.generation_hit_rate(), self.total_hits, self.total_lookups, FitnessCache::hit_rate(self.total_hits, self.total_lookups))
+    }
+}
+
+impl Backtest {
+    /// Takes the Backtest of a Player that has been run through the test data.
+    pub fn from_player<T: DataTrait>(player: &Player<T>) -> Backtest {
+        Backtest {
+            spend: player.spend,
+            spend_return: player.spend_return,
+            stocks_sold: player.stocks_sold.clone(),
+            stocks_purchased: player.stocks_purchased.clone()
+        }
+    }
+    /// Gives a Player the results of this Backtest, as if it had been run through the test data.
+    pub fn apply_to<T: DataTrait>(&self, player: &mut Player<T>) {
+        player.spend = self.spend;
+        player.spend_return = self.spend_return;
+        player.stocks_sold.clone_from(&self.stocks_sold);
+        player.stocks_purchased.clone_from(&self.stocks_purchased);
+    }
+}
+
+impl FitnessCache {
+    /// Creates an empty FitnessCache.
+    ///
+    /// # Arguments
+    /// * `capacity` - The number of Backtests kept between generations.
+    pub fn new_empty(capacity: usize) -> FitnessCache {
+        FitnessCache {
+            results: HashMap::new(),
+            capacity: capacity,
+            generation: 0,
+            pending: (0, 0),
+            generation_hits: 0,
+            generation_lookups: 0,
+            total_hits: 0,
+            total_lookups: 0
+        }
+    }
+    /// Returns true if a Backtest is stored for a key.
+    pub fn contains(&self, key: &(ScreenerKey, usize)) -> bool {
+        self.results.contains_key(key)
+    }
+    /// Gets the Backtest stored for a key, marking it as used.
+    pub fn get(&mut self, key: &(ScreenerKey, usize)) -> Option<&Backtest> {
+        let generation = self.generation;
+        self.results.get_mut(key).map(|(backtest, used)| {
+            *used = generation;
+            &*backtest
+        })
+    }
+    /// Stores a Backtest.
+    pub fn insert(&mut self, key: (ScreenerKey, usize), backtest: Backtest) {
+        self.results.insert(key, (backtest, self.generation));
+    }
+    /// Forgets every Backtest, as needed when the stocks that can be bought change, or at the end
+    /// of an iteration, since no later Player is run on its iteration.
+    pub fn clear(&mut self) {
+        self.results.clear();
+    }
+    /// Records the lookups of some Players run in the current generation.
+    ///
+    /// # Arguments
+    /// * `hits` - The number of Players that didn't need to be run.
+    /// * `lookups` - The number of Players.
+    pub fn record_lookups(&mut self, hits: usize, lookups: usize) {
+        self.pending.0 += hits;
+        self.pending.1 += lookups;
+    }
+    /// Ends a generation, making its lookups the generation's hit rate, then forgets the least
+    /// recently used Backtests beyond the capacity.
+    pub fn record_generation(&mut self) {
+        let (hits, lookups) = std::mem::take(&mut self.pending);
+        self.generation_hits = hits;
+        self.generation_lookups = lookups;
+        self.total_hits += hits;
+        self.total_lookups += lookups;
+        if self.results.len() > self.capacity {
+            let mut last_used = self.results.iter().map(|(key, (_, used))| (*used, key.clone())).collect::<Vec<_>>();
+            last_used.sort_by_key(|(used, _)| *used);
+            let excess = last_used.len() - self.capacity;
+            for (_, key) in last_used.into_iter().take(excess) {
+                self.results.remove(&key);
+            }
+        }
+        self.generation += 1;
+    }
+    /// Returns the percentage of the last generation's Players that were cached.
+    pub fn generation_hit_rate(&self) -> f64 {
+        FitnessCache::hit_rate(self.generation_hits, self.generation_lookups)
+    }
+    fn hit_rate(hits: usize, lookups: usize) -> f64 {
+        if lookups != 0 {100.0 * (hits as f64) / (lookups as f64)} else {0.0}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screener::{Rule, Screener};
+
+    fn key(threshold: usize) -> (ScreenerKey, usize) {
+        let screener = Screener {
+            screen: vec![(threshold, true, Rule::Gt)]
+        };
+        (screener.key(), 0)
+    }
+
+    fn backtest(spend: f64) -> Backtest {
+        Backtest {
+            spend: spend,
+            spend_return: 0.0,
+            stocks_sold: Vec::new(),
+            stocks_purchased: Vec::new()
+        }
+    }
+
+    #[test]
+    fn hits_are_counted_over_a_generation() {
+        let mut cache = FitnessCache::new_empty(10);
+        cache.record_lookups(3, 10);
+        cache.record_lookups(1, 4);
+        cache.record_generation();
+        assert_eq!((cache.generation_hits, cache.generation_lookups), (4, 14));
+        cache.record_lookups(5, 5);
+        cache.record_generation();
+        assert_eq!(cache.generation_hit_rate(), 100.0);
+        assert_eq!((cache.total_hits, cache.total_lookups), (9, 19));
+    }
+
+    #[test]
+    fn the_least_recently_used_backtests_are_forgotten() {
+        let mut cache = FitnessCache::new_empty(2);
+        cache.insert(key(10), backtest(1.0));
+        cache.insert(key(20), backtest(2.0));
+        cache.record_generation();
+        cache.insert(key(30), backtest(3.0));
+        assert_eq!(cache.get(&key(10)).map(|backtest| backtest.spend), Some(1.0));
+        cache.record_generation();
+        assert!(cache.contains(&key(10)));
+        assert!(!cache.contains(&key(20)));
+        assert!(cache.contains(&key(30)));
+    }
+}
@@ -1,29 +1,63 @@
 use rand::Rng;
-use std::{env::current_dir, error::Error, fmt, fs::File, io::{Write, Read}};
-use crossbeam::thread;
+use std::{fmt, fs::{self, File}, io::{Write, Read}, path::Path};
+use rayon::prelude::*;
+use std::{collections::HashSet, ops::Range, time::Instant};
 
+use crate::columnar::{Bitmap, ColumnarQuarters};
+use crate::crossover::Crossover;
 use crate::data_trait::DataTrait;
+use crate::error::Error;
+use crate::fields::FieldSelection;
+use crate::fitness::{Backtest, FitnessCache};
+use crate::hall_of_fame::HallOfFame;
+use crate::islands::IslandSettings;
+use crate::mutation::{MutationSettings, RateSchedule};
+use crate::niching::NichingSettings;
+use crate::observer::{Observer, Observers};
+use crate::percentile::PercentileSettings;
 use crate::player::Player;
 use crate::quarters::Quarters;
 use crate::screener::{Screener, Rule};
+use crate::selection::SelectionSettings;
+use crate::statistics::{FitnessSummary, GenerationStats};
+use crate::stopping::{Convergence, StoppingSettings};
+use crate::universe::{Universe, UniverseReport};
 
 pub static DEFAULT_TOURNEY_CONST: usize = 3;
 pub static DEFAULT_MUTATION_CONST: f64 = 0.7;
+pub static DEFAULT_HALL_OF_FAME_SIZE: usize = 10;
+pub static DEFAULT_FITNESS_CACHE_SIZE: usize = 10000;
 
 #[derive(Debug)]
 pub struct Game<T: DataTrait> {
     players: Vec<Player<T>>,
     quarters_initial: Quarters<f64>,
     quarters_actual: Quarters<T>,
-    current_quarter_index: usize,
+    columns_actual: ColumnarQuarters<T>,
     index_of_value: usize,
-    elitism: bool,
-    speciation: bool
+    eligibility: Vec<Bitmap>,
+    universe_report: Option<UniverseReport>,
+    fitness_cache: FitnessCache,
+    islands: IslandSettings,
+    generation: usize,
+    niching: NichingSettings,
+    field_spans: Vec<f64>,
+    selection: SelectionSettings,
+    crossover: Crossover,
+    mutation: MutationSettings,
+    limits: (Vec<T>, Vec<T>),
+    fields: FieldSelection,
+    hall_of_fame: HallOfFame<T>,
+    stopping: StoppingSettings,
+    stop_reports: Vec<String>,
+    start: Instant,
+    observers: Observers<T>,
+    iteration_best: Option<f64>
 }
 
 impl<T: DataTrait> fmt::Display for Game<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {  // Overly verbose
-        write!(f, "Game[players: {:?}, quarters_initial: {:?}, quarters_actual: {:?}, current_quarter_index: {}, index_of_value: {}, elitism: {}, speciation: {}]", self.players, self.quarters_initial, self.quarters_actual, self.current_quarter_index, self.index_of_value, self.elitism, self.speciation)
+        write!(f, "Game[players: {:?}, quarters_initial: {:?}, quarters_actual: {:?}, index_of_value: {}, niching: {}, selection: {}, crossover: {}, mutation: {}]", self.players, self.quarters_initial, self.quarters_actual, self.index_of_value, self.niching, self.selection, self.crossover, self.mutation)
     }
 }
 
@@ -33,44 +67,148 @@ impl<T: DataTrait> Game<T> {
     ///
     /// # Arguments
     /// * `num_of_players` - The number of players to create for the game.
-    /// * `size_of_data` - The length of Screener/DataRecord to use.
+    /// * `percentile_gap` - The percentile gap to use.
+    /// * `percentile_settings` - The percentile method and minimum sample size to use.
+    /// * `fields` - The banned and required fields, and the field used to value trades.
+    /// * `selection` - How parents are chosen, and which players are kept as elites.
+    /// * `niching` - How diversity is maintained.
     ///
     /// # Remarks
     /// Not currently implemented properly, just generates a standard random Game with players
     /// initialised between the test data element limits. Will likely need to be more sophisticated.
-    pub fn new_game(quarters_initial: Quarters<f64>, num_of_players: usize, percentile_gap: usize, elitism: bool, speciation: bool) -> Game<usize> {
-        // Get the banned indicies list
-        let banned_names = vec!["adj_close", "adj_factor", "adj_high", "adj_low", "adj_open", "adj_volume", "close", "high", "low", "open", "volume"];
-        let mut banned_indicies = Vec::new();
-        for (i, field_name) in quarters_initial.field_names.iter().enumerate() {
-            if banned_names.contains(&&field_name[0..]) {
-                banned_indicies.push(i);
-            }
-        }
-        // Create the actual quarters, and it's limits.
-        let quarters_actual = quarters_initial.create_percentile_quarters(percentile_gap);
+    /// Returns an error if the percentile gap doesn't evenly divide 100.
+    pub fn new_game(quarters_initial: Quarters<f64>, num_of_players: usize, percentile_gap: usize, percentile_settings: &PercentileSettings, fields: &FieldSelection, selection: &SelectionSettings, niching: &NichingSettings) -> Result<Game<usize>, Error> {
+        let quarters_actual = quarters_initial.create_percentile_quarters(percentile_gap, percentile_settings)?;
+        Ok(Game::new_game_with_quarters(quarters_initial, quarters_actual, num_of_players, percentile_gap, fields, selection, niching))
+    }
+    /// Create a new Game object over an already normalised copy of the data, initialised
+    /// randomly. The Screeners are evolved in the space of `quarters_actual`.
+    ///
+    /// # Arguments
+    /// * `quarters_initial` - The raw data, used for valuing trades.
+    /// * `quarters_actual` - The normalised data, used for screening.
+    /// * `num_of_players` - The number of players to create for the game.
+    /// * `percentile_gap` - The percentile gap to round thresholds to (ignored for f64 spaces).
+    /// * `fields` - The banned and required fields, and the field used to value trades.
+    /// * `selection` - How parents are chosen, and which players are kept as elites.
+    /// * `niching` - How diversity is maintained.
+    pub fn new_game_with_quarters(quarters_initial: Quarters<f64>, quarters_actual: Quarters<T>, num_of_players: usize, percentile_gap: usize, fields: &FieldSelection, selection: &SelectionSettings, niching: &NichingSettings) -> Game<T> {
+        // Find the limits of the actual quarters.
         let (l_limits, u_limits) = Game::calculate_cheap_limits(&quarters_actual);
         // Make players
         let mut players = Vec::new();
         for _i in 0..num_of_players {
-            players.push(Player::new_uniform_random((&l_limits, &u_limits), &banned_indicies, percentile_gap));
+            players.push(Player::new_uniform_random((&l_limits, &u_limits), &fields.banned_indicies, &fields.required_indicies, percentile_gap));
         }
+        let eligibility = quarters_initial.iter().map(|quarter| Bitmap::new(quarter.len(), true)).collect();
+        let columns_actual = ColumnarQuarters::from_quarters(&quarters_actual);
+        let field_spans = l_limits.iter().zip(u_limits.iter()).map(|(l, u)| if l < u {u.to_f64().unwrap() - l.to_f64().unwrap()} else {0.0}).collect();
         Game {
             players: players,
             quarters_initial: quarters_initial,
             quarters_actual: quarters_actual,
-            current_quarter_index: 0,
-            index_of_value: 0,
-            elitism: elitism,
-            speciation: speciation
+            columns_actual: columns_actual,
+            index_of_value: fields.index_of_value,
+            eligibility: eligibility,
+            universe_report: None,
+            fitness_cache: FitnessCache::new_empty(DEFAULT_FITNESS_CACHE_SIZE),
+            islands: IslandSettings::new_default(),
+            generation: 0,
+            niching: niching.clone(),
+            field_spans: field_spans,
+            selection: selection.clone(),
+            crossover: Crossover::Averaging,
+            mutation: MutationSettings::new_default(),
+            limits: (l_limits, u_limits),
+            fields: fields.clone(),
+            hall_of_fame: HallOfFame::new_empty(DEFAULT_HALL_OF_FAME_SIZE),
+            stopping: StoppingSettings::new_default(),
+            stop_reports: Vec::new(),
+            start: Instant::now(),
+            observers: Observers::new_empty(),
+            iteration_best: None
+        }
+    }
+    /// Restrict the stocks that can be bought to those in a Universe.
+    ///
+    /// # Arguments
+    /// * `universe` - The Universe of tradeable stocks.
+    ///
+    /// # Remarks
+    /// Stocks outside the Universe can still be sold, so a position is never stranded by its stock
+    /// leaving the Universe.
+    pub fn set_universe(&mut self, universe: &Universe) -> Result<(), Error> {
+        let (eligibility, report) = universe.eligibility(&self.quarters_initial)?;
+        println!("{}", universe);
+        for (time_id, eligible, total) in &report.quarter_counts {
+            println!("Universe {}: {} of {} stocks eligible.", time_id, eligible, total);
+        }
+        println!("{}", report);
+        self.eligibility = eligibility.iter().map(|quarter_eligibility| Bitmap::from_bools(quarter_eligibility)).collect();
+        self.universe_report = Some(report);
+        self.fitness_cache.clear();
+        Ok(())
+    }
+    /// Split the population into islands that evolve separately, exchanging their fittest
+    /// players every few generations.
+    ///
+    /// # Arguments
+    /// * `islands` - The number of islands, their topology and the migration settings.
+    pub fn set_islands(&mut self, islands: &IslandSettings) {
+        println!("{}", islands);
+        for (i, range) in islands.ranges(self.players.len()).iter().enumerate() {
+            println!("Island {}: {} players.", i, range.len());
         }
+        self.islands = islands.clone();
+    }
+    /// Set the crossover operator used to breed new players.
+    ///
+    /// # Arguments
+    /// * `crossover` - The crossover operator.
+    pub fn set_crossover(&mut self, crossover: &Crossover) {
+        self.crossover = crossover.clone();
+    }
+    /// Set the mutation operators and rates used to breed new players.
+    ///
+    /// # Arguments
+    /// * `mutation` - The mutation settings.
+    pub fn set_mutation(&mut self, mutation: &MutationSettings) {
+        self.mutation = mutation.clone();
+    }
+    /// Set the number of distinct Screeners kept in the hall of fame, emptying it.
+    ///
+    /// # Arguments
+    /// * `size` - The number of Screeners kept, 0 disabling the hall of fame.
+    pub fn set_hall_of_fame(&mut self, size: usize) {
+        self.hall_of_fame = HallOfFame::new_empty(size);
+    }
+    /// Set the number of Backtests the fitness cache keeps between generations, emptying it.
+    ///
+    /// # Arguments
+    /// * `capacity` - The number of Backtests kept, 0 keeping none past their generation.
+    pub fn set_fitness_cache(&mut self, capacity: usize) {
+        self.fitness_cache = FitnessCache::new_empty(capacity);
+    }
+    /// Set the criteria that stop an iteration before its last generation.
+    ///
+    /// # Arguments
+    /// * `stopping` - The stopping criteria.
+    pub fn set_stopping(&mut self, stopping: &StoppingSettings) {
+        self.stopping = stopping.clone();
+    }
+    /// Attach an Observer that receives the events of the Game as it runs.
+    ///
+    /// # Arguments
+    /// * `observer` - The Observer, called after any already attached.
+    pub fn add_observer(&mut self, observer: Box<dyn Observer<T>>) {
+        self.observers.push(observer);
     }
     fn calculate_cheap_limits(quarters: &Quarters<T>) -> (Vec<T>, Vec<T>) {
         let first_quarter = quarters.get(0).unwrap();
         let mut lower_limits = vec![T::max_value(); first_quarter.get(0).unwrap().len()];
         let mut upper_limits = vec![T::min_value(); first_quarter.get(0).unwrap().len()];
         for current_quarter in &quarters.quarters_vector {
-            for ref entry in &current_quarter.quarter_vector {
+            for entry in current_quarter.iter() {
                 for (&option_field, (lower_limit, upper_limit)) in entry.iter().zip(lower_limits.iter_mut().zip(upper_limits.iter_mut())) {
                     match option_field {
                         Some(field) => {
@@ -94,96 +232,355 @@ impl<T: DataTrait> Game<T> {
     /// * `generation_max` - The max number of generations to execute each time.
     /// * `iteration`- The number of iterations over the whole algorithm that should be performed.
     /// * `percentile_gap` - The percentile gap to use.
-    /// * `file_name` - The file name to save the run as.
-    pub fn run(&mut self, generation_max: usize, iteration: usize, percentile_gap: usize, file_name: String) {
+    /// * `path` - The path of the file to save the run as.
+    ///
+    /// # Remarks
+    /// An iteration stops early once one of the stopping criteria is met. Once the time limit is
+    /// reached every remaining iteration skips straight to its final run. Why each iteration
+    /// stopped is printed and saved.
+    ///
+    /// Returns an error if the run couldn't be saved.
+    pub fn run(&mut self, generation_max: usize, iteration: usize, percentile_gap: usize, path: &Path) -> Result<(), Error> {
+        self.start = Instant::now();
+        let start = self.start;
+        self.observers.notify(|observer| observer.run_started(generation_max, iteration));
         for i in 0..iteration {
+            self.iteration_best = None;
             if i != iteration - 1 {
-                for _j in 0..generation_max {
-                    self.perform_generation(DEFAULT_TOURNEY_CONST, DEFAULT_MUTATION_CONST, i, percentile_gap);
+                let mut convergence = Convergence::new(start);
+                let mut report = format!("Iteration {}: ran all {} generations.", i, generation_max);
+                for j in 0..generation_max {
+                    if self.stopping.is_out_of_time(start) {
+                        report = format!("Iteration {}: skipped after {} of {} generations, time limit reached.", i, j, generation_max);
+                        break;
+                    }
+                    let stats = self.perform_generation((j as f64) / (generation_max as f64), i, percentile_gap);
+                    if let Some(reason) = self.stopping.check(&mut convergence, &stats) {
+                        report = format!("Iteration {}: stopped after {} of {} generations, {}.", i, j + 1, generation_max, reason);
+                        break;
+                    }
                 }
+                println!("{}", report);
+                self.stop_reports.push(report);
             }
             self.perform_analytical_final_run(i);
             println!("Run {} complete!", i);
             self.print_best();
+            let stop_report = if i != iteration - 1 {self.stop_reports.last().map(|report| report.as_str())} else {None};
+            let best = Game::best_of(&self.players, self.quarters_actual.years());
+            self.observers.notify(|observer| observer.iteration_finished(i, stop_report, best));
+            self.fitness_cache.clear();
             if i != iteration - 1 {
                 self.soft_reset();
             }
         }
-        self.save(file_name);
+        for line in self.hall_of_fame.lines(&self.quarters_actual) {
+            println!("{}", line);
+        }
+        self.save(path)?;
+        let hall_of_fame = &self.hall_of_fame;
+        self.observers.notify(|observer| observer.run_finished(hall_of_fame));
+        Ok(())
     }
     /// Run through the training data, and generate a new population.
     ///
     /// # Arguments
-    /// * `k` - Constant used for tournament selection.
-    /// * `mut_const` - Constant used for mutation.
+    /// * `progress` - How far through the iteration's generations the run is, from 0 to 1.
     /// * `iteration` - The number of the current iteration.
     /// * `percentile_gap` - The percentile gap to use.
-    pub fn perform_generation(&mut self, k: usize, mut_const: f64, iteration: usize, percentile_gap: usize) {
+    ///
+    /// # Remarks
+    /// With more than one island, each island breeds only from its own players, and the islands
+    /// are bred in parallel. Migration happens after the players are run, so migrants replace
+    /// the least fit players of the islands they arrive on.
+    ///
+    /// With fitness sharing, selection uses each player's fitness divided by its niche count
+    /// within its island. With deterministic crowding, each pair of parents produces two
+    /// children which are run straight away, and each child replaces the parent it's closest
+    /// to only if it's fitter.
+    ///
+    /// Returns the statistics of the population that was run, before breeding, which are also
+    /// passed to the Observers once the generation is bred. The fitness cache hits include the
+    /// children run by crowding.
+    pub fn perform_generation(&mut self, progress: f64, iteration: usize, percentile_gap: usize) -> GenerationStats {
         self.run_one_game_generation(iteration);
         let players_with_payoff = self.players.iter().fold(0, |acc, player| if player.payoff() != 0.0 {acc + 1} else {acc});
-        println!("Player Count: {}, Average Profit: {:.3}%", players_with_payoff, self.average_payoff());
+        let average_payoff = self.average_payoff();
+        let best = self.find_best().map(|(payoff, player)| (payoff, player.clone()));
+        let best_payoff = best.as_ref().map(|(payoff, _)| *payoff);
+        let fitness_summary = FitnessSummary::from_fitness(&self.players.iter().map(|player| player.payoff_transform()).collect::<Vec<f64>>());
+        let trades = self.players.iter().map(|player| player.stocks_sold.len()).sum();
+        let unique_screeners = self.players.iter().map(|player| player.strategy.key()).collect::<HashSet<_>>().len();
+        println!("Player Count: {}, Average Profit: {:.3}%", players_with_payoff, average_payoff);
         self.print_best();
-        let mut new_population: Vec<Player<T>> = Vec::new();
-        // 1 player conditional elitism
-        let mut number_of_players_needed = self.players.len();
-        if self.elitism {
-            let best = self.find_best();
-            match best {
-                Some((_, best_player)) => {
-                    if best_player.stocks_sold.len() > 40 {
-                        let mut new_player = best_player.clone();
-                        new_player.soft_reset();
-                        new_population.push(new_player);
-                        number_of_players_needed -= 1;
+        if let RateSchedule::SelfAdaptive = self.mutation.schedule {
+            println!("Mean Mutation Scale: {:.3}", self.players.iter().map(|player| player.mutation_scale).sum::<f64>() / (self.players.len() as f64));
+        }
+        self.generation += 1;
+        let ranges = self.islands.ranges(self.players.len());
+        if ranges.len() > 1 {
+            for line in self.island_statistics(&ranges) {
+                println!("{}", line);
+            }
+            if self.islands.is_migration_generation(self.generation) {
+                self.migrate(&ranges);
+            }
+        }
+        // Niching
+        let screeners = self.players.iter().map(|player| &player.strategy).collect::<Vec<_>>();
+        let raw_fitness = self.players.iter().map(|player| player.payoff_transform()).collect::<Vec<f64>>();
+        let diversity = self.niching.diversity(&raw_fitness, &screeners, &self.field_spans);
+        println!("{}", diversity);
+        let fitness = if self.niching.sharing {
+            ranges.iter().flat_map(|range| {
+                let island_distances = self.niching.distances(&screeners[range.clone()], &self.field_spans);
+                self.niching.shared_fitness(&raw_fitness[range.clone()], &island_distances)
+            }).collect::<Vec<f64>>()
+        } else {
+            raw_fitness.clone()
+        };
+        // Breeding
+        let breed = |range: &Range<usize>| self.breed(range.clone(), &fitness[range.clone()], progress, percentile_gap);
+        let bred = if rayon::current_num_threads() == 1 {
+            ranges.iter().map(breed).collect::<Vec<_>>()
+        } else {
+            ranges.par_iter().map(breed).collect::<Vec<_>>()
+        };
+        if self.niching.crowding {
+            let mut children = Vec::new();
+            let mut parents = Vec::new();
+            for (island_children, island_parents) in bred {
+                children.extend(island_children);
+                parents.extend(island_parents);
+            }
+            self.run_players(&mut children, iteration);
+            let mut new_population = self.players.clone();
+            let mut replaced = 0;
+            for ((first_parent, second_parent), pair) in parents.into_iter().zip(children.chunks(2)) {
+                let distance = |parent: usize, child: &Player<T>| self.niching.distance(&self.players[parent].strategy, &child.strategy, &self.field_spans);
+                let matches = if distance(first_parent, &pair[0]) + distance(second_parent, &pair[1]) <= distance(first_parent, &pair[1]) + distance(second_parent, &pair[0]) {
+                    [(first_parent, &pair[0]), (second_parent, &pair[1])]
+                } else {
+                    [(first_parent, &pair[1]), (second_parent, &pair[0])]
+                };
+                for (parent, child) in matches.iter() {
+                    if child.payoff_transform() > raw_fitness[*parent] {
+                        new_population[*parent] = (*child).clone();
+                        replaced += 1;
                     }
                 }
-                None => {}
+            }
+            println!("Crowding replaced {} of {} parents.", replaced, new_population.len());
+            for player in new_population.iter_mut() {
+                player.soft_reset();
+            }
+            self.players = new_population;
+        } else {
+            self.players = bred.into_iter().flat_map(|(island_children, _)| island_children).collect();
+        }
+        self.fitness_cache.record_generation();
+        println!("Fitness cache hits: {} of {} ({:.1}%)", self.fitness_cache.generation_hits, self.fitness_cache.generation_lookups, self.fitness_cache.generation_hit_rate());
+        let stats = GenerationStats {
+            iteration: iteration,
+            generation: self.generation - 1,
+            players_with_payoff: players_with_payoff,
+            average_payoff: average_payoff,
+            best_payoff: best_payoff,
+            fitness: fitness_summary,
+            diversity: diversity,
+            trades: trades,
+            unique_screeners: unique_screeners,
+            cache_hits: self.fitness_cache.generation_hits,
+            elapsed: self.start.elapsed().as_secs_f64()
+        };
+        self.observers.notify(|observer| observer.generation_evaluated(&stats));
+        if let Some((payoff, player)) = &best {
+            if self.iteration_best.is_none_or(|iteration_best| *payoff > iteration_best) {
+                self.iteration_best = Some(*payoff);
+                self.observers.notify(|observer| observer.new_best(&stats, player));
             }
         }
-        let mut tracker = 0;
-        for _i in 0..number_of_players_needed {
-            let mut counter = 0;
-            let mut select_one = self.tourney_select(k);
-            let mut select_two = self.tourney_select(k);
-            while self.speciation & select_one.is_similar_to(select_two, 0.25) {
-                select_one = self.tourney_select(k);
-                select_two = self.tourney_select(k);
-                counter += 1;
-                if counter == 100 {
-                    tracker += 1;
-                    break;
+        stats
+    }
+    /// Breeds an island. Returns the children, and with deterministic crowding the indicies of
+    /// the parents of each pair of children.
+    ///
+    /// # Arguments
+    /// * `range` - The players of the island, after being run through the training data.
+    /// * `fitness` - The fitness of each player of the island used for selection.
+    /// * `progress` - How far through the iteration's generations the run is, from 0 to 1.
+    /// * `percentile_gap` - The percentile gap to use.
+    ///
+    /// # Remarks
+    /// Without crowding the new generation of the island is its elites, followed by children of
+    /// parents picked by the selection operator. With crowding the parents are paired at random, and each pair
+    /// produces two children, so a player left without a pair survives unchanged.
+    fn breed(&self, range: Range<usize>, fitness: &[f64], progress: f64, percentile_gap: usize) -> (Vec<Player<T>>, Vec<(usize, usize)>) {
+        let island = &self.players[range.clone()];
+        let limits = (&self.limits.0[..], &self.limits.1[..]);
+        let mut new_population: Vec<Player<T>> = Vec::new();
+        if self.niching.crowding {
+            let mut order = range.collect::<Vec<usize>>();
+            rand::thread_rng().shuffle(&mut order);
+            let parents = order.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect::<Vec<_>>();
+            for (first_parent, second_parent) in &parents {
+                let (first_parent, second_parent) = (&self.players[*first_parent], &self.players[*second_parent]);
+                new_population.push(first_parent.crossover(second_parent, &self.crossover, percentile_gap).mutate(&self.mutation, progress, limits, &self.fields, percentile_gap));
+                new_population.push(second_parent.crossover(first_parent, &self.crossover, percentile_gap).mutate(&self.mutation, progress, limits, &self.fields, percentile_gap));
+            }
+            return (new_population, parents);
+        }
+        // Elitism, the best players that satisfy the elite predicate
+        let years = self.quarters_actual.years();
+        let mut elites = island.iter().filter(|player| (player.spend_return > player.spend) & (player.stocks_sold.len() > self.selection.elite_min_sales) & player.payoff_per_year(years).is_finite()).collect::<Vec<_>>();
+        elites.sort_by(|a, b| b.payoff_per_year(years).total_cmp(&a.payoff_per_year(years)));
+        for elite in elites.into_iter().take(self.selection.elites.min(island.len())) {
+            let mut new_player = elite.clone();
+            new_player.soft_reset();
+            new_population.push(new_player);
+        }
+        let number_of_players_needed = island.len() - new_population.len();
+        let parents = self.selection.method.select(fitness, 2 * number_of_players_needed);
+        for pair in parents.chunks_exact(2) {
+            let (select_one, select_two) = (&island[pair[0]], &island[pair[1]]);
+            new_population.push(select_one.crossover(select_two, &self.crossover, percentile_gap).mutate(&self.mutation, progress, limits, &self.fields, percentile_gap));
+        }
+        (new_population, Vec::new())
+    }
+    /// Copies the fittest players of each island over the least fit players of the islands it
+    /// sends to, as given by the island topology.
+    ///
+    /// # Arguments
+    /// * `ranges` - The players on each island.
+    fn migrate(&mut self, ranges: &[Range<usize>]) {
+        let by_fitness = |players: &[Player<T>], range: &Range<usize>| {
+            let mut order = range.clone().collect::<Vec<usize>>();
+            order.sort_by(|a, b| players[*b].payoff_transform().total_cmp(&players[*a].payoff_transform()));
+            order
+        };
+        let emigrants = ranges.iter().map(|range| {
+            by_fitness(&self.players, range).into_iter().take(self.islands.migrants).map(|i| self.players[i].clone()).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+        let mut migrated = 0;
+        for (source, migrants) in emigrants.iter().enumerate() {
+            for target in self.islands.topology.targets(source, ranges.len()) {
+                let least_fit = by_fitness(&self.players, &ranges[target]).into_iter().rev();
+                for (i, migrant) in least_fit.zip(migrants.iter()) {
+                    self.players[i] = migrant.clone();
+                    migrated += 1;
                 }
             }
-            new_population.push(select_one.dumb_crossover(select_two, percentile_gap).lazy_mutate(mut_const, percentile_gap));
         }
-        println!("Speciation terminated {:?} times.", tracker);
-        self.players = new_population;
+        println!("Migrated {} players between {} islands.", migrated, ranges.len());
+    }
+    /// Returns a line of statistics for each island.
+    ///
+    /// # Arguments
+    /// * `ranges` - The players on each island.
+    pub fn island_statistics(&self, ranges: &[Range<usize>]) -> Vec<String> {
+        let years = self.quarters_actual.years();
+        ranges.iter().enumerate().map(|(i, range)| {
+            let island = &self.players[range.clone()];
+            let players_with_payoff = island.iter().filter(|player| player.payoff() != 0.0).count();
+            let best = match Game::best_of(island, years) {
+                Some((payoff, _)) => format!("{:.3}%", payoff),
+                None => "Didn't exist".to_string()
+            };
+            format!("Island {}: Players: {}, Player Count: {}, Average Profit: {:.3}%, Best Payoff: {}", i, island.len(), players_with_payoff, Game::average_payoff_of(island, years), best)
+        }).collect()
     }
+    /// Runs every Player through all of the test data.
     ///
+    /// # Arguments
+    /// * `iteration` - The number of the current iteration.
+    ///
+    /// # Remarks
+    /// Players don't affect each other, so each is run through every quarter on its own, spread
+    /// over the current rayon thread pool. With a single thread the Players are run in order on
+    /// the calling thread, and the results are the same either way.
+    ///
+    /// Only one Player is run for each distinct used portion of a Screener that isn't already in
+    /// the fitness cache. Every other Player is given the cached Backtest. Every Player is offered
+    /// to the hall of fame.
     fn run_one_game_generation(&mut self, iteration: usize) {
-        while self.current_quarter_index < self.quarters_actual.len() - 1 {
-            self.next_quarter(iteration);
-        }
-        self.next_quarter(iteration);
-        self.current_quarter_index = 0;
+        let mut players = std::mem::take(&mut self.players);
+        self.run_players(&mut players, iteration);
+        self.players = players;
     }
-    /// Runs through the next quarter of test data.
+    /// Runs some Players through all of the test data, using and filling the fitness cache, and
+    /// records the hits of the cache.
     ///
     /// # Arguments
+    /// * `players` - The Players to run.
     /// * `iteration` - The number of the current iteration.
-    fn next_quarter(&mut self, iteration: usize) {
-        let quarter = self.quarters_actual.get(self.current_quarter_index).unwrap();
-        let float_quarter = self.quarters_initial.get(self.current_quarter_index).unwrap();
+    fn run_players(&mut self, players: &mut [Player<T>], iteration: usize) {
+        let keys = players.iter().map(|player| (player.strategy.key(), iteration)).collect::<Vec<_>>();
+        let mut pending = HashSet::new();
+        let to_run = keys.iter().map(|key| !self.fitness_cache.contains(key) && pending.insert(key)).collect::<Vec<bool>>();
+        let columns_actual = &self.columns_actual;
+        let quarters_initial = &self.quarters_initial;
+        let eligibility = &self.eligibility;
         let index_of_value = self.index_of_value;
-        let player_iter = self.players.iter_mut();
-        thread::scope(|s| {
-            for mut player in player_iter {
-                s.spawn(move |_| {
-                    quarter.select_for_player(&float_quarter, &mut player, index_of_value, iteration);
-                });
+        let evaluate = |(player, run): (&mut Player<T>, &bool)| {
+            if *run {
+                Game::evaluate_player(player, columns_actual, quarters_initial, eligibility, index_of_value, iteration);
+            }
+        };
+        if rayon::current_num_threads() == 1 {
+            players.iter_mut().zip(to_run.iter()).for_each(evaluate);
+        } else {
+            players.par_iter_mut().zip(to_run.par_iter()).for_each(evaluate);
+        }
+        for ((player, key), run) in players.iter().zip(keys.iter()).zip(to_run.iter()) {
+            if *run {
+                self.fitness_cache.insert(key.clone(), Backtest::from_player(player));
             }
-        }).unwrap();
-        self.current_quarter_index += 1;
+        }
+        for ((player, key), run) in players.iter_mut().zip(keys.iter()).zip(to_run.iter()) {
+            if !*run {
+                self.fitness_cache.get(key).unwrap().apply_to(player);
+            }
+        }
+        self.hall_of_fame.consider(players, &keys.into_iter().map(|(key, _)| key).collect::<Vec<_>>(), self.generation, iteration, self.quarters_actual.years());
+        let runs = to_run.iter().filter(|run| **run).count();
+        self.fitness_cache.record_lookups(players.len() - runs, players.len());
+    }
+    /// Runs a Player through every quarter of test data, in order.
+    ///
+    /// # Arguments
+    /// * `player` - The Player to run.
+    /// * `columns_actual` - The screened data.
+    /// * `quarters_initial` - The raw data, used for valuing trades.
+    /// * `eligibility` - Which stocks can be bought in each quarter.
+    /// * `index_of_value` - The index of the field used to value trades.
+    /// * `iteration` - The number of the current iteration.
+    fn evaluate_player(player: &mut Player<T>, columns_actual: &ColumnarQuarters<T>, quarters_initial: &Quarters<f64>, eligibility: &[Bitmap], index_of_value: usize, iteration: usize) {
+        for (quarter, (float_quarter, eligible)) in columns_actual.quarters_vector.iter().zip(quarters_initial.iter().zip(eligibility.iter())) {
+            quarter.select_for_player(float_quarter, player, eligible, index_of_value, iteration);
+        }
+    }
+    /// Runs a single Screener through every quarter of test data, without changing the
+    /// population or the fitness cache. Returns the Player that ran it.
+    ///
+    /// # Arguments
+    /// * `screener` - The Screener to evaluate, in the space of the Game's quarters.
+    /// * `iteration` - The iteration whose stock assignments are used.
+    pub fn evaluate(&self, screener: &Screener<T>, iteration: usize) -> Player<T> {
+        let mut player = Player::new_player(screener.clone());
+        Game::evaluate_player(&mut player, &self.columns_actual, &self.quarters_initial, &self.eligibility, self.index_of_value, iteration);
+        player
+    }
+    /// Returns the current population.
+    pub fn players(&self) -> &[Player<T>] {
+        &self.players
+    }
+    /// Returns the best distinct Screeners evaluated so far.
+    pub fn hall_of_fame(&self) -> &HallOfFame<T> {
+        &self.hall_of_fame
+    }
+    /// Returns the quarters the Screeners are evolved over.
+    pub fn quarters(&self) -> &Quarters<T> {
+        &self.quarters_actual
     }
     /// Perform a final generation of the algorithm, purely to analyse the potential screeners
     ///
@@ -191,30 +588,37 @@ impl<T: DataTrait> Game<T> {
     /// * `iteration` - The number of the current iteration.
     pub fn perform_analytical_final_run(&mut self, iteration: usize) {
         self.run_one_game_generation(iteration);
+        self.fitness_cache.record_generation();
         let best = self.find_best();
         match best {
             Some((_, bestie)) => {
                 println!("Best");
-                println!("{:?}", bestie.stocks_sold.iter().map(|(_, _, stock)| stock.stock_id.to_string()).collect::<Vec<_>>());
+                println!("{:?}", bestie.stocks_sold.iter().map(|(_, _, stock_id)| stock_id.to_string()).collect::<Vec<_>>());
                 println!("{:?} - {:?}", bestie.spend_return, bestie.spend);
             }
             None => {
                 println!("Default");
-                println!("{:?}", self.players[0].stocks_sold.iter().map(|(_, _, stock)| stock.stock_id.to_string()).collect::<Vec<_>>());
+                println!("{:?}", self.players[0].stocks_sold.iter().map(|(_, _, stock_id)| stock_id.to_string()).collect::<Vec<_>>());
                 println!("{:?} - {:?}", self.players[0].spend_return, self.players[0].spend);
             }
         }
     }
     /// Compute the average percentage gain across the entire population.
     pub fn average_payoff(&self) -> f64 {
-        let years = self.quarters_actual.years();
-        let filtered_players = self.players.iter().filter(|player| player.spend_return > player.spend).collect::<Vec<_>>();
+        Game::average_payoff_of(&self.players, self.quarters_actual.years())
+    }
+    /// Compute the average percentage gain across some players.
+    fn average_payoff_of(players: &[Player<T>], years: f64) -> f64 {
+        let filtered_players = players.iter().filter(|player| player.spend_return > player.spend).collect::<Vec<_>>();
         filtered_players.iter().fold(0.0, |acc, player| acc + player.payoff_per_year(years)) / (filtered_players.len() as f64)
     }
     ///
     pub fn find_best(&self) -> Option<(f64, &Player<T>)> {
-        let years = self.quarters_actual.years();
-        let filtered_players = self.players.iter().filter(|player| player.spend_return > player.spend).collect::<Vec<_>>();
+        Game::best_of(&self.players, self.quarters_actual.years())
+    }
+    /// Find the player with the highest percentage gain per year among some players.
+    fn best_of(players: &[Player<T>], years: f64) -> Option<(f64, &Player<T>)> {
+        let filtered_players = players.iter().filter(|player| player.spend_return > player.spend).collect::<Vec<_>>();
         let mut filtered_players_iter = filtered_players.iter();
         match filtered_players_iter.next() {
             Some(player) => {
@@ -256,86 +660,87 @@ impl<T: DataTrait> Game<T> {
             player.soft_reset();
         }
     }
-    /// Perform a tournament selection of size k within the current list of Players. The fitness
-    /// function is the current payoff value of each player.
-    ///
-    /// # Arguments
-    /// * `k` - Constant used for tournament selection (default: DEFAULT_TOURNEY_CONST = 3).
-    ///
-    /// # Remarks
-    /// This will fail at runtime if called with k = 0.
-    fn tourney_select(&self, k: usize) -> &Player<T> {
-        let mut rng = rand::thread_rng();
-        let mut candidate = &self.players[rng.gen_range(0, self.players.len())];
-        if k == 0 {
-            panic!("Tournament Selection with k = 0 occurred. Unrecoverable error.");
-        } else {
-            for _i in 1..k {
-                let next_candidate = &self.players[rng.gen_range(0, self.players.len())];
-                if next_candidate.payoff_transform() > candidate.payoff_transform() {
-                    candidate = next_candidate;
-                }
-            }
-            candidate
+    /// Save the current set of strategies in a human readable format, creating the directory of
+    /// the file if it doesn't exist. The players are written from the highest return to the
+    /// lowest, and the population itself isn't reordered.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|why| Error::Io(directory.to_path_buf(), why))?;
+        }
+        let mut file = File::create(path).map_err(|why| Error::Io(path.to_path_buf(), why))?;
+        let mut lines = Vec::new();
+        if let Some(report) = &self.universe_report {
+            lines.push(report.to_string());
+        }
+        let ranges = self.islands.ranges(self.players.len());
+        if ranges.len() > 1 {
+            lines.extend(self.island_statistics(&ranges));
+        }
+        lines.extend(self.stop_reports.iter().cloned());
+        lines.extend(self.hall_of_fame.lines(&self.quarters_actual));
+        for line in lines {
+            file.write_all(format!("{}\n", line).as_bytes()).map_err(|why| Error::Io(path.to_path_buf(), why))?;
         }
-    }
-    /// Save the current set of strategies in a human readable format
-    pub fn save(&mut self, file_name: String) {
-        let mut path = current_dir().unwrap();
-        path.pop(); path.push(file_name);
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create file {:?}: {}", path, why.description()),
-            Ok(file) => file,
-        };
         let years = self.quarters_actual.years();
-        self.players.sort_by(|a_p, b_p| {
+        let mut players = self.players.iter().collect::<Vec<&Player<T>>>();
+        players.sort_by(|a_p, b_p| {
             let a_p_return = if a_p.spend != 0.0 {a_p.spend_return / a_p.spend} else {0.0};
             let b_p_return = if b_p.spend != 0.0 {b_p.spend_return / b_p.spend} else {0.0};
-            a_p_return.partial_cmp(&b_p_return).unwrap()
+            a_p_return.total_cmp(&b_p_return)
         });
-        self.players = self.players.clone().into_iter().rev().collect();
-        for player in &self.players {
-            let output_string = format!["Payoff: {:.3}%, Screen: {:?}, Sold List: {:?}\n", player.payoff_per_year(years), player.format_screen(&self.quarters_actual), player.stocks_sold.iter().map(|(_, _, stock)| stock.stock_id.to_string()).collect::<Vec<_>>()];
-            match file.write_all(output_string.as_bytes()) {
-                Err(why) => panic!("couldn't write to file {:?}: {}", path, why.description()),
-                Ok(_) => println!("successfully wrote to {:?}", path)
-            }
+        for player in players.into_iter().rev() {
+            let output_string = format!["Payoff: {:.3}%, Screen: {:?}, Sold List: {:?}\n", player.payoff_per_year(years), player.format_screen(&self.quarters_actual), player.stocks_sold.iter().map(|(_, _, stock_id)| stock_id.to_string()).collect::<Vec<_>>()];
+            file.write_all(output_string.as_bytes()).map_err(|why| Error::Io(path.to_path_buf(), why))?;
+            println!("successfully wrote to {:?}", path);
         }
+        Ok(())
     }
-    ///
-    pub fn read_file(&mut self, file_name: String) {
-        // Create a path to the desired file
-        let mut path = current_dir().unwrap();
-        path.pop(); path.push(file_name);
-        let display = path.display();
-
+    /// Reads a Screener saved by save, or written in the same format, into the population.
+    pub fn read_file(&mut self, path: &Path) -> Result<(), Error> {
         // Open the path in read-only mode, returns `io::Result<File>`
-        let mut file = match File::open(&path) {
-            Err(why) => panic!("couldn't open {}: {}", display, why.description()),
-            Ok(file) => file,
-        };
+        let mut file = File::open(path).map_err(|why| Error::Io(path.to_path_buf(), why))?;
 
         // Read the file contents into a string, returns `io::Result<usize>`
         let mut s = String::new();
-        match file.read_to_string(&mut s) {
-            Err(why) => panic!("couldn't read {}: {}", display, why.description()),
-            Ok(_) => self.read_string(s, true),
-        }
+        file.read_to_string(&mut s).map_err(|why| Error::Io(path.to_path_buf(), why))?;
+        self.read_string(s, true)
     }
+    /// Replaces the population with a single Player using the Screener written in a string.
     ///
-    pub fn read_string(&mut self, screener_string: String, from_file: bool) {
+    /// # Arguments
+    /// * `screener_string` - The Screener, like "[(name, Lt, value), (name, Gt, value)]".
+    /// * `from_file` - Whether the string was read from a file, so has quoted names and ends
+    ///   with a newline.
+    ///
+    /// Returns an error if the string isn't a Screener, or names a field that isn't in the data.
+    pub fn read_string(&mut self, screener_string: String, from_file: bool) -> Result<(), Error> {
         let mut screener_vector = Vec::new();
         // looks like [(name, rule, value), (name, rule, value), (name, rule, value), .., (name, rule, value)]
         println!("{:?}", screener_string);
-        let s;
-        if from_file {
-            s = screener_string[2..(screener_string.len() - 3)].to_string();    // remove the starting [( and ending )]\n
-        } else {
-            s = screener_string[2..(screener_string.len() - 2)].to_string();    // remove the starting [( and ending )]
+        let end = if from_file {3} else {2};
+        if (screener_string.len() < 2 + end) || !screener_string.starts_with("[(") {
+            return Err(Error::Parse(format!("{:?} isn't a screener like \"[(name, Lt, value), (name, Gt, value)]\".", screener_string)));
         }
+        let s = screener_string[2..(screener_string.len() - end)].to_string();    // remove the starting [( and ending )] or )]\n
         println!("{:?}", s);
         let split: Vec<&str> = s.split("), (").collect();
         println!("{:?}", split);
+        for screen_rule in &split {
+            let string_elements: Vec<&str> = screen_rule.split(", ").collect();
+            if string_elements.len() != 3 {
+                return Err(Error::Parse(format!("{:?} isn't a rule like \"(name, Lt, value)\".", screen_rule)));
+            }
+            let string_name = if from_file {string_elements[0].trim_matches('"')} else {string_elements[0]};
+            if !self.quarters_actual.field_names.iter().any(|name| name == string_name) {
+                return Err(Error::Parse(format!("the screener uses the field {:?}, which isn't in the data.", string_name)));
+            }
+            if !(string_elements[1].contains("Lt") | string_elements[1].contains("Gt")) {
+                return Err(Error::Parse(format!("{:?} isn't a rule, use Lt or Gt.", string_elements[1])));
+            }
+            if string_elements[2].parse::<f64>().ok().and_then(T::from).is_none() {
+                return Err(Error::Parse(format!("{:?} isn't a value for the field {:?}.", string_elements[2], string_name)));
+            }
+        }
         let mut last_name_checked = None;
         for screen_rule in split {
             let string_elements: Vec<&str> = screen_rule.split(", ").collect();  // this is a vector [name, rule, value]
@@ -343,12 +748,7 @@ impl<T: DataTrait> Game<T> {
             'a: for (i, name) in self.quarters_actual.field_names.iter().enumerate() {
                 match last_name_checked {
                     None => {
-                        let string_name;
-                        if from_file {
-                            string_name = &string_elements[0][1..(string_elements[0].len()-1)];
-                        } else {
-                            string_name = &string_elements[0];
-                        }
+                        let string_name = if from_file {string_elements[0].trim_matches('"')} else {string_elements[0]};
                         let string_rule = string_elements[1];
                         let string_value = string_elements[2];
                         last_name_checked = Some(i);
@@ -361,12 +761,7 @@ impl<T: DataTrait> Game<T> {
                     }
                     Some(j) => {
                         if i > j {
-                            let string_name;
-                            if from_file {
-                                string_name = &string_elements[0][1..(string_elements[0].len()-1)];
-                            } else {
-                                string_name = &string_elements[0];
-                            }
+                            let string_name = if from_file {string_elements[0].trim_matches('"')} else {string_elements[0]};
                             let string_rule = string_elements[1];
                             let string_value = string_elements[2];
                             last_name_checked = Some(i);
@@ -404,6 +799,100 @@ impl<T: DataTrait> Game<T> {
             screen: screener_vector
         })];
         println!("{:?}", self.players);
+        Ok(())
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::{DataRecord, StockID, TimeID};
+    use crate::quarter::Quarter;
+    use crate::ticker::Ticker;
+
+    /// Four quarters of 30 stocks, with a price and one other field.
+    fn quarters() -> Quarters<f64> {
+        let quarters_vector = (1..=4).map(|period| {
+            let time_id = TimeID::from_name(&format!("2010-Q{}", period)).unwrap();
+            Quarter::new((0..30).map(|i| DataRecord {
+                record: vec![Some((10 + (i * 7 + period * 3) % 13) as f64), Some(((i * 5 + period) % 11) as f64)],
+                stock_id: StockID {
+                    name: Ticker::intern(&format!("G{}", i)),
+                    time_id: time_id,
+                    iteration: 0
+                }
+            }).collect(), time_id)
+        }).collect::<Vec<Quarter<f64>>>();
+        Quarters {
+            field_names: vec!["close".to_string(), "pe".to_string()],
+            starting_time: quarters_vector[0].time_id,
+            ending_time: quarters_vector[3].time_id,
+            quarters_vector: quarters_vector
+        }
+    }
+
+    fn game(num_of_players: usize) -> Game<f64> {
+        let fields = FieldSelection {
+            banned_indicies: vec![0],
+            required_indicies: vec![1],
+            index_of_value: 0
+        };
+        Game::new_game_with_quarters(quarters(), quarters(), num_of_players, 10, &fields, &SelectionSettings::new_default(), &NichingSettings::new_default())
+    }
+
+    fn payoffs_on_threads(game: &mut Game<f64>, players: &[Player<f64>], threads: usize) -> Vec<f64> {
+        game.players = players.to_vec();
+        game.fitness_cache.clear();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| game.run_one_game_generation(0));
+        game.players.iter().map(|player| player.payoff()).collect()
+    }
+
+    #[test]
+    fn players_are_run_the_same_on_any_number_of_threads() {
+        let mut game = game(40);
+        let players = game.players.clone();
+        let sequential = payoffs_on_threads(&mut game, &players, 1);
+        assert_eq!(payoffs_on_threads(&mut game, &players, 4), sequential);
+        assert!(sequential.iter().any(|payoff| *payoff != 0.0));
+    }
+
+    /// Records the name of every event but new_best, which depends on the random population.
+    struct EventLog(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl Observer<f64> for EventLog {
+        fn run_started(&mut self, generation_max: usize, iterations: usize) {
+            self.0.lock().unwrap().push(format!("run_started {} {}", generation_max, iterations));
+        }
+        fn generation_evaluated(&mut self, stats: &GenerationStats) {
+            self.0.lock().unwrap().push(format!("generation_evaluated {} {}", stats.iteration, stats.generation));
+        }
+        fn iteration_finished(&mut self, iteration: usize, stop_report: Option<&str>, _best: Option<(f64, &Player<f64>)>) {
+            self.0.lock().unwrap().push(format!("iteration_finished {} {}", iteration, stop_report.is_some()));
+        }
+        fn run_finished(&mut self, _hall_of_fame: &HallOfFame<f64>) {
+            self.0.lock().unwrap().push("run_finished".to_string());
+        }
+    }
+
+    #[test]
+    fn observers_see_every_event_of_a_run_in_order() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut game = game(10);
+        game.add_observer(Box::new(EventLog(events.clone())));
+        let directory = std::env::temp_dir().join(format!("game-observer-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let result = game.run(2, 2, 10, &directory.join("output.txt"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        result.unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![
+            "run_started 2 2",
+            "generation_evaluated 0 0",
+            "generation_evaluated 0 1",
+            "iteration_finished 0 true",
+            "iteration_finished 1 false",
+            "run_finished"
+        ]);
+    }
+}
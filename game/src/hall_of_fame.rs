@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::data_trait::DataTrait;
+use crate::player::Player;
+use crate::quarters::Quarters;
+use crate::screener::{Screener, ScreenerKey};
+
+/// A Screener as it was when it entered the HallOfFame.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct HallOfFameEntry<T: DataTrait> {
+    pub strategy: Screener<T>,
+    pub key: ScreenerKey,
+    pub generation: usize,
+    pub iteration: usize,
+    pub payoff_per_year: f64,
+    pub payoff_transform: f64,
+    pub spend: f64,
+    pub spend_return: f64,
+    pub stocks_sold: usize
+}
+
+/// The best distinct Screeners ever evaluated, from fittest to least fit by percentage gain per
+/// year. Only profitable Screeners are kept.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct HallOfFame<T: DataTrait> {
+    pub size: usize,
+    entries: Vec<HallOfFameEntry<T>>
+}
+
+impl<T: DataTrait> fmt::Display for HallOfFame<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HallOfFame[size: {}, entries: {}]", self.size, self.entries.len())
+    }
+}
+
+impl<T: DataTrait> HallOfFame<T> {
+    /// Creates an empty HallOfFame that keeps up to size Screeners.
+    pub fn new_empty(size: usize) -> HallOfFame<T> {
+        HallOfFame {
+            size: size,
+            entries: Vec::new()
+        }
+    }
+    /// Returns the entries, from fittest to least fit.
+    pub fn entries(&self) -> &[HallOfFameEntry<T>] {
+        &self.entries
+    }
+    /// Offers some Players that have just been run through the test data.
+    ///
+    /// # Arguments
+    /// * `players` - The Players.
+    /// * `keys` - The key of the used portion of each Player's Screener.
+    /// * `generation` - The number of generations performed so far.
+    /// * `iteration` - The number of the current iteration.
+    /// * `years` - The number of years that the algorithm has run over.
+    ///
+    /// # Remarks
+    /// A Screener already in the HallOfFame is only replaced by a fitter run of the same Screener,
+    /// so every entry is distinct.
+    pub fn consider(&mut self, players: &[Player<T>], keys: &[ScreenerKey], generation: usize, iteration: usize, years: f64) {
+        if self.size == 0 {
+            return;
+        }
+        for (player, key) in players.iter().zip(keys.iter()) {
+            let payoff_per_year = player.payoff_per_year(years);
+            if (player.spend_return <= player.spend) | !payoff_per_year.is_finite() {
+                continue;
+            }
+            if (self.entries.len() == self.size) && (payoff_per_year <= self.entries[self.size - 1].payoff_per_year) {
+                continue;
+            }
+            if let Some(existing) = self.entries.iter().position(|entry| entry.key == *key) {
+                if payoff_per_year <= self.entries[existing].payoff_per_year {
+                    continue;
+                }
+                self.entries.remove(existing);
+            }
+            let position = self.entries.iter().position(|entry| entry.payoff_per_year < payoff_per_year).unwrap_or(self.entries.len());
+            self.entries.insert(position, HallOfFameEntry {
+                strategy: player.strategy.clone(),
+                key: key.clone(),
+                generation: generation,
+                iteration: iteration,
+                payoff_per_year: payoff_per_year,
+                payoff_transform: player.payoff_transform(),
+                spend: player.spend,
+                spend_return: player.spend_return,
+                stocks_sold: player.stocks_sold.len()
+            });
+            self.entries.truncate(self.size);
+        }
+    }
+    /// Returns a line for each entry, naming the fields of its Screener.
+    ///
+    /// # Arguments
+    /// * `quarters` - The quarters the Screeners were evolved over, for the field names.
+    pub fn lines(&self, quarters: &Quarters<T>) -> Vec<String> {
+        self.entries.iter().enumerate().map(|(rank, entry)| {
+            format!("Hall of Fame {}: Payoff: {:.3}%, Fitness: {:.3}, Generation: {}, Iteration: {}, Sold: {}, Return: {:.3} - {:.3}, Screen: {:?}", rank + 1, entry.payoff_per_year, entry.payoff_transform, entry.generation, entry.iteration, entry.stocks_sold, entry.spend_return, entry.spend, entry.strategy.format_screen(quarters))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screener::Rule;
+
+    /// A Player with one used field at threshold, which returned spend_return on a spend of 1.
+    fn player(threshold: f64, spend_return: f64) -> Player<f64> {
+        let mut player = Player::new_player(Screener {
+            screen: vec![(threshold, true, Rule::Lt)]
+        });
+        player.spend = 1.0;
+        player.spend_return = spend_return;
+        player
+    }
+
+    fn consider(hall_of_fame: &mut HallOfFame<f64>, players: &[Player<f64>]) {
+        let keys = players.iter().map(|player| player.strategy.key()).collect::<Vec<ScreenerKey>>();
+        hall_of_fame.consider(players, &keys, 0, 1, 1.0);
+    }
+
+    fn payoffs(hall_of_fame: &HallOfFame<f64>) -> Vec<f64> {
+        hall_of_fame.entries().iter().map(|entry| entry.payoff_per_year.round()).collect()
+    }
+
+    #[test]
+    fn keeps_the_fittest_profitable_screeners_in_order() {
+        let mut hall_of_fame = HallOfFame::new_empty(2);
+        consider(&mut hall_of_fame, &[player(1.0, 1.1), player(2.0, 0.5), player(3.0, 1.3), player(4.0, 1.2)]);
+        assert_eq!(payoffs(&hall_of_fame), vec![30.0, 20.0]);
+        let mut empty = HallOfFame::new_empty(0);
+        consider(&mut empty, &[player(1.0, 1.1)]);
+        assert!(empty.entries().is_empty());
+    }
+
+    #[test]
+    fn a_screener_is_only_kept_once() {
+        let mut hall_of_fame = HallOfFame::new_empty(3);
+        consider(&mut hall_of_fame, &[player(1.0, 1.1), player(1.0, 1.3), player(1.0, 1.2)]);
+        assert_eq!(payoffs(&hall_of_fame), vec![30.0]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fmt, ops::Range};
+
+/// Which islands each island sends its migrants to.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum Topology {
+    /// Island i sends to island i + 1, and the last island sends to the first.
+    Ring,
+    /// Every island sends to every other island.
+    FullyConnected
+}
+
+/// How the population is split into islands, and how players migrate between them.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct IslandSettings {
+    pub count: usize,
+    pub topology: Topology,
+    pub migration_interval: usize,
+    pub migrants: usize
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Topology::Ring => write!(f, "Ring"),
+            Topology::FullyConnected => write!(f, "FullyConnected")
+        }
+    }
+}
+
+impl fmt::Display for IslandSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IslandSettings[count: {}, topology: {}, migration_interval: {}, migrants: {}]", self.count, self.topology, self.migration_interval, self.migrants)
+    }
+}
+
+impl Topology {
+    /// Parse a Topology from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "ring" or "full".
+    pub fn from_name(name: &str) -> Option<Topology> {
+        match name {
+            "ring" => Some(Topology::Ring),
+            "full" => Some(Topology::FullyConnected),
+            _ => None
+        }
+    }
+    /// Returns the islands that an island sends its migrants to.
+    ///
+    /// # Arguments
+    /// * `source` - The index of the sending island.
+    /// * `count` - The number of islands.
+    pub fn targets(&self, source: usize, count: usize) -> Vec<usize> {
+        if count < 2 {
+            return Vec::new();
+        }
+        match self {
+            Topology::Ring => vec![(source + 1) % count],
+            Topology::FullyConnected => (0..count).filter(|target| *target != source).collect()
+        }
+    }
+}
+
+impl IslandSettings {
+    /// The default settings: a single island, so no migration.
+    pub fn new_default() -> IslandSettings {
+        IslandSettings {
+            count: 1,
+            topology: Topology::Ring,
+            migration_interval: 5,
+            migrants: 2
+        }
+    }
+    /// Splits a population into the ranges of players on each island. Island sizes differ by at
+    /// most one, and no island is empty.
+    ///
+    /// # Arguments
+    /// * `population_size` - The number of players.
+    pub fn ranges(&self, population_size: usize) -> Vec<Range<usize>> {
+        let count = self.count.clamp(1, population_size.max(1));
+        let (size, remainder) = (population_size / count, population_size % count);
+        let mut start = 0;
+        (0..count).map(|i| {
+            let end = start + size + if i < remainder {1} else {0};
+            let range = start..end;
+            start = end;
+            range
+        }).collect()
+    }
+    /// Returns true if players migrate after the given generation.
+    ///
+    /// # Arguments
+    /// * `generation` - The number of generations performed so far, counting this one.
+    pub fn is_migration_generation(&self, generation: usize) -> bool {
+        (self.count > 1) & (self.migrants > 0) & (self.migration_interval > 0) && generation.is_multiple_of(self.migration_interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn islands(count: usize) -> IslandSettings {
+        let mut settings = IslandSettings::new_default();
+        settings.count = count;
+        settings
+    }
+
+    #[test]
+    fn islands_differ_in_size_by_at_most_one() {
+        assert_eq!(islands(3).ranges(10), vec![0..4, 4..7, 7..10]);
+        assert_eq!(islands(5).ranges(3), vec![0..1, 1..2, 2..3]);
+        assert_eq!(islands(1).ranges(4), vec![0..4]);
+    }
+
+    #[test]
+    fn migrants_follow_the_topology() {
+        assert_eq!(Topology::Ring.targets(3, 4), vec![0]);
+        assert_eq!(Topology::FullyConnected.targets(1, 4), vec![0, 2, 3]);
+        assert!(Topology::Ring.targets(0, 1).is_empty());
+    }
+
+    #[test]
+    fn migration_happens_every_interval() {
+        let settings = islands(2);
+        assert!(!settings.is_migration_generation(4));
+        assert!(settings.is_migration_generation(5));
+        assert!(settings.is_migration_generation(10));
+        assert!(!islands(1).is_migration_generation(5));
+    }
+}
@@ -0,0 +1,77 @@
+//! A genetic algorithm that evolves stock screeners over quarterly, monthly or annual fundamental
+//! data.
+//!
+//! The usual flow is to load a set of Quarters (see `experiment::load_quarters`, which uses the
+//! cache), build a Game over them with `Game::new_game_with_quarters`, configure it with its
+//! setters, and then either evolve it with `Game::run` or evaluate a single Screener with
+//! `Game::evaluate`. `experiment::run` and `experiment::test` do all of this from a Settings, as
+//! the binary does. The results of a run are in `Game::players` and `Game::hall_of_fame`, and
+//! `Game::save` writes them to a file.
+//!
+//! Anything that can fail, like loading data or saving a run, returns an `Error` rather than
+//! panicking.
+//!
+//! The types of the API are re-exported here, and the functions the binary uses are in
+//! `experiment`. Every other module is private to the crate.
+
+mod data_trait;
+mod error;
+
+mod ticker;
+mod cache;
+mod data_record;
+mod columnar;
+mod crossover;
+mod fields;
+mod fitness;
+mod hall_of_fame;
+mod islands;
+mod alignment;
+mod loading;
+mod mutation;
+mod niching;
+mod observer;
+mod paths;
+mod normalisation;
+mod percentile;
+mod universe;
+mod validation;
+mod settings;
+mod screener;
+mod selection;
+mod statistics;
+mod stopping;
+mod quarter;
+mod quarters;
+mod player;
+mod game;
+pub mod experiment;
+
+pub use crate::alignment::{AlignedBy, AlignmentReport, AlignmentSettings};
+pub use crate::cache::Cache;
+pub use crate::crossover::Crossover;
+pub use crate::data_record::{DataRecord, Frequency, StockID, TimeID};
+pub use crate::data_trait::DataTrait;
+pub use crate::error::Error;
+pub use crate::fields::{FieldSelection, FieldSettings, DEFAULT_BANNED_FIELDS};
+pub use crate::game::{Game, DEFAULT_FITNESS_CACHE_SIZE, DEFAULT_HALL_OF_FAME_SIZE, DEFAULT_MUTATION_CONST, DEFAULT_TOURNEY_CONST};
+pub use crate::hall_of_fame::{HallOfFame, HallOfFameEntry};
+pub use crate::islands::{IslandSettings, Topology};
+pub use crate::loading::LoadSettings;
+pub use crate::mutation::{MutationSettings, RateSchedule, ThresholdMutation};
+pub use crate::niching::{Diversity, NichingSettings};
+pub use crate::normalisation::Normalisation;
+pub use crate::observer::Observer;
+pub use crate::paths::{PathSettings, PATH_VARIABLES};
+pub use crate::percentile::{PercentileMethod, PercentileSettings};
+pub use crate::player::Player;
+pub use crate::quarter::{Quarter, RecordMut};
+pub use crate::quarters::Quarters;
+pub use crate::screener::{Rule, Screener, ScreenerKey};
+pub use crate::selection::{Selection, SelectionSettings};
+pub use crate::settings::Settings;
+pub use crate::statistics::{FitnessSummary, GenerationStats};
+pub use crate::stopping::{StopReason, StoppingSettings};
+pub use crate::ticker::Ticker;
+pub use crate::universe::{Universe, UniverseReport};
+pub use crate::validation::{FieldQuality, StockQuality, ValidationAction, ValidationReport, ValidationSettings, QUALITY_FIELD};
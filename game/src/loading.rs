@@ -0,0 +1,96 @@
+use std::fmt;
+
+use crate::alignment::AlignmentSettings;
+use crate::data_record::{Frequency, TimeID};
+use crate::validation::ValidationSettings;
+
+/// How the data files are turned into Quarters.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct LoadSettings {
+    /// How long each period of the data is. Each Quarter of the loaded Quarters is one period.
+    pub frequency: Frequency,
+    /// The first period loaded. Records of periods starting earlier are skipped.
+    pub start: Option<TimeID>,
+    /// The last period loaded. Records of periods ending later are skipped. Without one the
+    /// Quarters end at the period with the most stocks.
+    pub end: Option<TimeID>,
+    /// How fiscal periods are moved onto calendar periods.
+    pub alignment: AlignmentSettings,
+    /// How the loaded data is checked, and what is done with offending data.
+    pub validation: ValidationSettings
+}
+
+impl fmt::Display for LoadSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format_time = |time_id: &Option<TimeID>| time_id.map(|time_id| time_id.to_string()).unwrap_or_else(|| "None".to_string());
+        write!(f, "LoadSettings[frequency: {}, start: {}, end: {}, alignment: {}, validation: {}]", self.frequency, format_time(&self.start), format_time(&self.end), self.alignment, self.validation)
+    }
+}
+
+impl LoadSettings {
+    /// The default settings: every quarter in the data, ending at the one with the most stocks.
+    pub fn new_default() -> LoadSettings {
+        LoadSettings {
+            frequency: Frequency::Quarterly,
+            start: None,
+            end: None,
+            alignment: AlignmentSettings::new_default(),
+            validation: ValidationSettings::new_default()
+        }
+    }
+    /// A description of the settings that differs whenever they would load the data differently.
+    pub fn key(&self) -> String {
+        format!("{} {}", self, self.alignment.key())
+    }
+    /// Returns true if a period is within the start and end, which may be of other frequencies.
+    pub fn contains(&self, time_id: &TimeID) -> bool {
+        self.start.is_none_or(|start| time_id.first_month() >= start.first_month()) && self.end.is_none_or(|end| time_id.last_month() <= end.last_month())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn between(start: Option<&str>, end: Option<&str>) -> LoadSettings {
+        let mut settings = LoadSettings::new_default();
+        settings.start = start.map(|name| TimeID::from_name(name).unwrap());
+        settings.end = end.map(|name| TimeID::from_name(name).unwrap());
+        settings
+    }
+
+    fn contains(settings: &LoadSettings, name: &str) -> bool {
+        settings.contains(&TimeID::from_name(name).unwrap())
+    }
+
+    #[test]
+    fn everything_is_within_no_range() {
+        let settings = between(None, None);
+        assert!(contains(&settings, "1990-Q1"));
+        assert!(contains(&settings, "2030-M12"));
+    }
+
+    #[test]
+    fn start_and_end_are_inclusive() {
+        let settings = between(Some("2010-Q2"), Some("2012-Q3"));
+        assert!(!contains(&settings, "2010-Q1"));
+        assert!(contains(&settings, "2010-Q2"));
+        assert!(contains(&settings, "2012-Q3"));
+        assert!(!contains(&settings, "2012-Q4"));
+        let open_ended = between(Some("2010-Q2"), None);
+        assert!(contains(&open_ended, "2040-Q1"));
+        assert!(!contains(&open_ended, "2009-Q4"));
+    }
+
+    #[test]
+    fn ranges_compare_periods_of_other_frequencies_by_month() {
+        let settings = between(Some("2010-Q2"), Some("2012"));
+        assert!(!contains(&settings, "2010-M03"));
+        assert!(contains(&settings, "2010-M04"));
+        assert!(contains(&settings, "2012-M12"));
+        assert!(!contains(&settings, "2010"));
+        assert!(contains(&settings, "2012-Q4"));
+        assert!(!contains(&settings, "2013-M01"));
+    }
+}
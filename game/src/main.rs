@@ -1,31 +1,30 @@
-use std::env;
+use std::{env, path::PathBuf, process, str::FromStr, time::Duration};
 
-pub mod data_trait;
-
-pub mod data_record;
-pub mod screener;
-pub mod quarter;
-pub mod quarters;
-pub mod player;
-pub mod game;
-
-use crate::quarters::Quarters;
-use crate::game::Game;
+use game::experiment::{run, test};
+use game::{AlignmentSettings, Crossover, Error, Frequency, PathSettings, PercentileMethod, Normalisation, RateSchedule, Selection, Settings, ThresholdMutation, TimeID, Topology, Universe, ValidationAction, DEFAULT_TOURNEY_CONST};
 
 fn main() {
+    if let Err(why) = try_main() {
+        eprintln!("Error: {}", why);
+        process::exit(why.exit_code());
+    }
+}
+
+fn try_main() -> Result<(), Error> {
     // Defaults
-    let mut population_sizes = vec![100];
-    let mut generation_maxs = vec![10];
-    let mut iterations = vec![3];
-    let mut percentiles = vec![10];
-    let mut elitism = false;
-    let mut speciation = false;
-    let mut runs = 10;
+    let mut settings = Settings::new_default();
+    settings.paths = PathSettings::from_environment();
+    let mut normalisation_name = "percentile".to_string();
+    let mut winsor_percent = 1.0;
+    let mut selection_name = "tournament".to_string();
+    let mut tournament_size = DEFAULT_TOURNEY_CONST;
+    let mut rank_pressure = 1.5;
+    let mut truncation = 0.5;
 
     // Arguments
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        panic!("Less than 1 argument submitted.");
+        return Err(Error::Config("Less than 1 argument submitted.".to_string()));
     }
     let mut args_iter = args.iter(); args_iter.next();
 
@@ -41,71 +40,166 @@ fn main() {
     println!("Supplied argument pairs: {:?}", arg_pairs);
 
     for (arg_one, arg_two) in arg_pairs {
+        settings.normalisation = match Normalisation::from_name(&normalisation_name, winsor_percent) {
+            Some(normalisation) => normalisation,
+            None => return Err(Error::Config(format!("Unknown normalisation {:?}.", normalisation_name)))
+        };
+        settings.selection.method = match Selection::from_name(&selection_name, tournament_size, rank_pressure, truncation) {
+            Some(selection) => selection,
+            None => return Err(Error::Config(format!("Unknown selection {:?}.", selection_name)))
+        };
         match (&arg_one[0..arg_one.len()], &arg_two[0..arg_two.len()]) {
-            ("-run", _) => run(&population_sizes, &generation_maxs, &iterations, &percentiles, &elitism, &speciation, &runs),
-            ("-test", "") => test_file(&percentiles, &elitism, &speciation),
-            ("-test", screener_string) => test_string(&percentiles, screener_string.to_string(), &elitism, &speciation),
-            ("-lambda", x) => population_sizes = vector_from_string(x.to_string()),
-            ("-gen_max", x) => generation_maxs = vector_from_string(x.to_string()),
-            ("-iterations", x) => iterations = vector_from_string(x.to_string()),
-            ("-percentiles", x) => percentiles = vector_from_string(x.to_string()),
-            ("-elitism", _) => elitism = true,
-            ("-speciation", _) => speciation = true,
-            ("-runs", x) => runs = vector_from_string(x.to_string())[0],
+            ("-run", _) => run(&settings)?,
+            ("-test", "") => test(&settings, None)?,
+            ("-test", screener_string) => test(&settings, Some(screener_string.to_string()))?,
+            ("-lambda", x) => settings.population_sizes = vector_from_string(x.to_string())?,
+            ("-gen_max", x) => settings.generation_maxs = vector_from_string(x.to_string())?,
+            ("-iterations", x) => settings.iterations = vector_from_string(x.to_string())?,
+            ("-percentiles", x) => settings.percentiles = vector_from_string(x.to_string())?,
+            ("-elitism", _) => settings.selection.elites = 1,
+            ("-elites", x) => settings.selection.elites = vector_from_string(x.to_string())?[0],
+            ("-elite_min_sales", x) => settings.selection.elite_min_sales = vector_from_string(x.to_string())?[0],
+            ("-crossover", x) => settings.crossover = match Crossover::from_name(x) {
+                Some(crossover) => crossover,
+                None => return Err(Error::Config(format!("Unknown crossover {:?}.", x)))
+            },
+            ("-mutation", x) => settings.mutation.threshold = match ThresholdMutation::from_name(x) {
+                Some(threshold) => threshold,
+                None => return Err(Error::Config(format!("Unknown mutation {:?}.", x)))
+            },
+            ("-mutation_rate", x) => settings.mutation.threshold_rate = parse_number(arg_one, x)?,
+            ("-mutation_sigma", x) => settings.mutation.sigma = parse_non_negative(arg_one, x)?,
+            ("-toggle_rate", x) => settings.mutation.toggle_rate = parse_number(arg_one, x)?,
+            ("-flip_rate", x) => settings.mutation.flip_rate = parse_number(arg_one, x)?,
+            ("-swap_rate", x) => settings.mutation.swap_rate = parse_number(arg_one, x)?,
+            ("-mutation_schedule", x) => settings.mutation.schedule = match RateSchedule::from_name(x) {
+                Some(schedule) => schedule,
+                None => return Err(Error::Config(format!("Unknown mutation schedule {:?}.", x)))
+            },
+            ("-mutation_end", x) => settings.mutation.end_scale = parse_number(arg_one, x)?,
+            ("-mutation_tau", x) => settings.mutation.tau = parse_non_negative(arg_one, x)?,
+            ("-selection", x) => selection_name = x.to_string(),
+            ("-tournament_size", x) => tournament_size = match vector_from_string(x.to_string())?[0] {
+                0 => return Err(Error::Config("-tournament_size must be at least 1.".to_string())),
+                size => size
+            },
+            ("-rank_pressure", x) => rank_pressure = parse_number(arg_one, x)?,
+            ("-truncation", x) => truncation = parse_number(arg_one, x)?,
+            ("-speciation", _) => settings.niching.sharing = true,
+            ("-sharing", _) => settings.niching.sharing = true,
+            ("-crowding", _) => settings.niching.crowding = true,
+            ("-niche_radius", x) => settings.niching.sigma = parse_positive(arg_one, x)?,
+            ("-sharing_alpha", x) => settings.niching.alpha = parse_number(arg_one, x)?,
+            ("-field_weight", x) => settings.niching.field_weight = parse_number(arg_one, x)?,
+            ("-runs", x) => settings.runs = vector_from_string(x.to_string())?[0],
+            ("-normalisation", x) => normalisation_name = x.to_string(),
+            ("-winsorise", x) => winsor_percent = parse_number(arg_one, x)?,
+            ("-percentile_method", x) => settings.percentile_settings.method = match PercentileMethod::from_name(x) {
+                Some(method) => method,
+                None => return Err(Error::Config(format!("Unknown percentile method {:?}.", x)))
+            },
+            ("-min_samples", x) => settings.percentile_settings.min_samples = vector_from_string(x.to_string())?[0],
+            ("-min_market_cap", x) => settings.universe.min_market_cap = Some(parse_number(arg_one, x)?),
+            ("-min_price", x) => settings.universe.min_price = Some(parse_number(arg_one, x)?),
+            ("-min_volume", x) => settings.universe.min_volume = Some(parse_number(arg_one, x)?),
+            ("-market_cap_field", x) => settings.universe.market_cap_field = x.to_string(),
+            ("-price_field", x) => {
+                settings.universe.price_field = x.to_string();
+                settings.loading.validation.price_field = x.to_string();
+            },
+            ("-volume_field", x) => settings.universe.volume_field = x.to_string(),
+            ("-min_history", x) => settings.universe.min_history = vector_from_string(x.to_string())?[0],
+            ("-universe_tickers", x) => settings.universe.tickers = Some(Universe::read_ticker_list(x)?),
+            ("-universe_exclude", x) => settings.universe.excluded = Universe::read_ticker_list(x)?,
+            ("-universe_exchanges", x) => settings.universe.exchanges = Some(x.split(',').map(|exchange| exchange.to_string()).collect()),
+            ("-exchange_map", x) => settings.universe.exchange_map = Universe::read_exchange_map(x)?,
+            ("-allowed", x) => settings.fields.allowed = Some(names_from_string(x)),
+            ("-banned", x) => settings.fields.banned = Some(names_from_string(x)),
+            ("-required", x) => settings.fields.required = names_from_string(x),
+            ("-value_field", x) => settings.fields.value_field = Some(x.to_string()),
+            ("-frequency", x) => settings.loading.frequency = match Frequency::from_name(x) {
+                Some(frequency) => frequency,
+                None => return Err(Error::Config(format!("Unknown frequency {:?}.", x)))
+            },
+            ("-start", x) => settings.loading.start = Some(parse_time(arg_one, x)?),
+            ("-end", x) => settings.loading.end = Some(parse_time(arg_one, x)?),
+            ("-fiscal_year_ends", x) => settings.loading.alignment.fiscal_year_ends = AlignmentSettings::read_fiscal_year_ends(x)?,
+            ("-period_end_field", x) => settings.loading.alignment.period_end_field = Some(x.to_string()),
+            ("-validate", _) => settings.loading.validation.enabled = true,
+            ("-validation_action", x) => settings.loading.validation.action = match ValidationAction::from_name(x) {
+                Some(action) => action,
+                None => return Err(Error::Config(format!("Unknown validation action {:?}.", x)))
+            },
+            ("-outlier_threshold", x) => settings.loading.validation.outlier_threshold = parse_number(arg_one, x)?,
+            ("-min_coverage", x) => settings.loading.validation.min_coverage = parse_number(arg_one, x)?,
+            ("-non_negative", x) => settings.loading.validation.non_negative_fields = Some(names_from_string(x)),
+            ("-max_price_ratio", x) => settings.loading.validation.max_price_ratio = parse_number(arg_one, x)?,
+            ("-root", x) => settings.paths.root = PathBuf::from(x),
+            ("-data", x) => settings.paths.data = PathBuf::from(x),
+            ("-input", x) => settings.paths.input = PathBuf::from(x),
+            ("-output", x) => settings.paths.output = PathBuf::from(x),
+            ("-cache", x) => settings.cache_directory = Some(x.to_string()),
+            ("-no_cache", _) => settings.cache_directory = None,
+            ("-islands", x) => settings.islands.count = vector_from_string(x.to_string())?[0],
+            ("-topology", x) => settings.islands.topology = match Topology::from_name(x) {
+                Some(topology) => topology,
+                None => return Err(Error::Config(format!("Unknown island topology {:?}.", x)))
+            },
+            ("-migration_interval", x) => settings.islands.migration_interval = vector_from_string(x.to_string())?[0],
+            ("-migrants", x) => settings.islands.migrants = vector_from_string(x.to_string())?[0],
+            ("-hall_of_fame", x) => settings.hall_of_fame = vector_from_string(x.to_string())?[0],
+            ("-fitness_cache", x) => settings.fitness_cache = vector_from_string(x.to_string())?[0],
+            ("-plateau", x) => settings.stopping.plateau = Some(vector_from_string(x.to_string())?[0]),
+            ("-plateau_tolerance", x) => settings.stopping.plateau_tolerance = parse_number(arg_one, x)?,
+            ("-min_diversity", x) => settings.stopping.min_diversity = Some(parse_number(arg_one, x)?),
+            ("-time_limit", x) => settings.stopping.time_limit = Some(Duration::try_from_secs_f64(parse_non_negative(arg_one, x)?).map_err(|why| Error::Config(format!("{} {:?} isn't a duration: {}.", arg_one, x, why)))?),
+            ("-target_fitness", x) => settings.stopping.target_fitness = Some(parse_number(arg_one, x)?),
+            ("-threads", x) => settings.threads = vector_from_string(x.to_string())?[0],
             _ => {}
         }
     }
+    Ok(())
 }
 
-fn vector_from_string(string: String) -> Vec<usize> {
-    let split: Vec<_> = string.split(",").collect();
-    split.iter().map(|string_percent| {
-        if string_percent.contains("[") & string_percent.contains("]") {
-            string_percent[1..(string_percent.len() - 1)].parse::<usize>().unwrap()
-        } else if string_percent.contains("[") {
-            string_percent[1..string_percent.len()].parse::<usize>().unwrap()
-        } else if string_percent.contains("]") {
-            string_percent[0..(string_percent.len() - 1)].parse::<usize>().unwrap()
-        } else {
-            string_percent.parse::<usize>().unwrap()
-        }
-    }).collect::<Vec<usize>>()
+fn parse_number<N: FromStr>(flag: &str, string: &str) -> Result<N, Error> {
+    string.parse::<N>().map_err(|_| Error::Parse(format!("{} expects a number, not {:?}.", flag, string)))
 }
 
-fn run(population_sizes: &Vec<usize>, generation_maxs: &Vec<usize>, iterations: &Vec<usize>, percentiles: &Vec<usize>, elitism: &bool, speciation: &bool, runs: &usize) {
-    println!("Running algorithm with runs={}, lambda={:?}, gen_max={:?}, iter={:?}, percentiles={:?}, elitism={}, speciation={}", runs, population_sizes, generation_maxs, iterations, percentiles, elitism, speciation);
-    println!("This is going to execute the genetic algorithm {:?} times.", runs * population_sizes.len() * generation_maxs.len() * iterations.len() * percentiles.len());
-    for i in 0..*runs {
-        for iteration in iterations {
-            let quarters = Quarters::<f64>::new_quarters_from_default_file(*iteration);
-            for population_size in population_sizes {
-                for generation_max in generation_maxs {
-                    for percentile in percentiles {
-                        let mut game = Game::<usize>::new_game(quarters.clone(), *population_size, *percentile, *elitism, *speciation);
-                        game.run(*generation_max, *iteration, *percentile, format!("test-data/output-r{}-perc{}-g{}-i{}-pop{}.txt", i, *percentile, *generation_max, *iteration, *population_size));
-                    }
-                }
-            }
-        }
+fn parse_non_negative(flag: &str, string: &str) -> Result<f64, Error> {
+    match parse_number::<f64>(flag, string)? {
+        number if number.is_finite() && (number >= 0.0) => Ok(number),
+        number => Err(Error::Config(format!("{} must be a finite number of at least 0, not {}.", flag, number)))
     }
 }
 
-fn test_file(percentiles: &Vec<usize>, elitism: &bool, speciation: &bool) {
-    println!("Running test_file with lambda=1, gen_max=N/A, iter=1, percentiles=[{:?}], elitism={}, speciation={}", percentiles[0], elitism, speciation);
-    let read_quarters = Quarters::<f64>::new_quarters_from_default_file(1);
+fn parse_positive(flag: &str, string: &str) -> Result<f64, Error> {
+    match parse_number::<f64>(flag, string)? {
+        number if number.is_finite() && (number > 0.0) => Ok(number),
+        number => Err(Error::Config(format!("{} must be a finite number above 0, not {}.", flag, number)))
+    }
+}
 
-    let mut game = Game::<usize>::new_game(read_quarters, 1, percentiles[0], *elitism, *speciation);
-    game.read_file("test-data/input.txt".to_string());
-    game.perform_analytical_final_run(0);
-    game.print_best();
+fn parse_time(flag: &str, string: &str) -> Result<TimeID, Error> {
+    TimeID::from_name(string).ok_or_else(|| Error::Parse(format!("{} expects a period like 2010, 2010-Q1 or 2010-M03, not {:?}.", flag, string)))
 }
 
-fn test_string(percentiles: &Vec<usize>, screener_string: String, elitism: &bool, speciation: &bool) {
-    println!("Running test_string with lambda=1, gen_max=N/A, iter=1, percentiles=[{:?}], string={:?}, elitism={}, speciation={}", percentiles[0], screener_string, elitism, speciation);
-    let read_quarters = Quarters::<f64>::new_quarters_from_default_file(1);
+fn vector_from_string(string: String) -> Result<Vec<usize>, Error> {
+    let split: Vec<_> = string.split(",").collect();
+    split.iter().map(|string_percent| {
+        let number = if string_percent.contains("[") & string_percent.contains("]") {
+            &string_percent[1..(string_percent.len() - 1)]
+        } else if string_percent.contains("[") {
+            &string_percent[1..string_percent.len()]
+        } else if string_percent.contains("]") {
+            &string_percent[0..(string_percent.len() - 1)]
+        } else {
+            string_percent
+        };
+        number.parse::<usize>().map_err(|_| Error::Parse(format!("{:?} isn't a list of whole numbers, like [1,2,3].", string)))
+    }).collect::<Result<Vec<usize>, Error>>()
+}
 
-    let mut game = Game::<usize>::new_game(read_quarters, 1, percentiles[0], *elitism, *speciation);
-    game.read_string(screener_string, false);
-    game.perform_analytical_final_run(0);
-    game.print_best();
+fn names_from_string(string: &str) -> Vec<String> {
+    string.trim_start_matches('[').trim_end_matches(']').split(',').map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect()
 }
+
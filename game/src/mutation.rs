@@ -0,0 +1,291 @@
+use std::fmt;
+use rand::{Rng, distributions::{Distribution, Normal}};
+
+use crate::data_trait::DataTrait;
+use crate::fields::FieldSelection;
+use crate::game::DEFAULT_MUTATION_CONST;
+use crate::screener::{Rule, Screener};
+
+/// How a threshold is perturbed when it mutates.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum ThresholdMutation {
+    /// Uniformly within +/-10% of its value (the original mutation).
+    Lazy,
+    /// By a normally distributed step, with a standard deviation of sigma times the field's range.
+    Gaussian
+}
+
+/// How the mutation rates change over a run.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum RateSchedule {
+    /// The rates never change.
+    Constant,
+    /// The rates are scaled linearly from 1 at the first generation to end_scale at the last.
+    Linear,
+    /// Every player carries its own scale, inherited from its parents and perturbed log-normally
+    /// with tau at each mutation, so the rates evolve with the players.
+    SelfAdaptive
+}
+
+/// The mutation operators applied to each child, and their rates.
+///
+/// # Remarks
+/// Each rate is the expected number of fields of a Screener that the operator changes, except
+/// swap_rate, which is the probability of a single swap.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct MutationSettings {
+    pub threshold: ThresholdMutation,
+    pub threshold_rate: f64,
+    pub sigma: f64,
+    /// Rules turned on or off, never turning on a banned field or turning off a required one.
+    pub toggle_rate: f64,
+    /// Used rules switched between Lt and Gt.
+    pub flip_rate: f64,
+    /// A used rule moved to an unused field, with a random threshold.
+    pub swap_rate: f64,
+    pub schedule: RateSchedule,
+    pub end_scale: f64,
+    pub tau: f64
+}
+
+impl fmt::Display for ThresholdMutation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThresholdMutation::Lazy => write!(f, "Lazy"),
+            ThresholdMutation::Gaussian => write!(f, "Gaussian")
+        }
+    }
+}
+
+impl fmt::Display for RateSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateSchedule::Constant => write!(f, "Constant"),
+            RateSchedule::Linear => write!(f, "Linear"),
+            RateSchedule::SelfAdaptive => write!(f, "SelfAdaptive")
+        }
+    }
+}
+
+impl fmt::Display for MutationSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MutationSettings[threshold: {}, threshold_rate: {}, sigma: {}, toggle_rate: {}, flip_rate: {}, swap_rate: {}, schedule: {}, end_scale: {}, tau: {}]", self.threshold, self.threshold_rate, self.sigma, self.toggle_rate, self.flip_rate, self.swap_rate, self.schedule, self.end_scale, self.tau)
+    }
+}
+
+impl ThresholdMutation {
+    /// Parse a ThresholdMutation from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "lazy" or "gaussian".
+    pub fn from_name(name: &str) -> Option<ThresholdMutation> {
+        match name {
+            "lazy" => Some(ThresholdMutation::Lazy),
+            "gaussian" => Some(ThresholdMutation::Gaussian),
+            _ => None
+        }
+    }
+}
+
+impl RateSchedule {
+    /// Parse a RateSchedule from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "constant", "linear" or "adaptive".
+    pub fn from_name(name: &str) -> Option<RateSchedule> {
+        match name {
+            "constant" => Some(RateSchedule::Constant),
+            "linear" => Some(RateSchedule::Linear),
+            "adaptive" => Some(RateSchedule::SelfAdaptive),
+            _ => None
+        }
+    }
+}
+
+impl MutationSettings {
+    /// The default settings: the original lazy threshold mutation at a constant rate, and no
+    /// other operators.
+    pub fn new_default() -> MutationSettings {
+        MutationSettings {
+            threshold: ThresholdMutation::Lazy,
+            threshold_rate: DEFAULT_MUTATION_CONST,
+            sigma: 0.1,
+            toggle_rate: 0.0,
+            flip_rate: 0.0,
+            swap_rate: 0.0,
+            schedule: RateSchedule::Constant,
+            end_scale: 0.1,
+            tau: 0.2
+        }
+    }
+    /// Returns the mutation scale a child starts with. Only self-adaptive rates change it.
+    ///
+    /// # Arguments
+    /// * `parent_scale` - The scale inherited from the parents.
+    pub fn child_scale(&self, parent_scale: f64) -> f64 {
+        match self.schedule {
+            RateSchedule::SelfAdaptive => {
+                let step = Normal::new(0.0, self.tau).sample(&mut rand::thread_rng());
+                (parent_scale * step.exp()).clamp(0.01, 100.0)
+            },
+            _ => parent_scale
+        }
+    }
+    /// Returns the factor every rate is multiplied by.
+    ///
+    /// # Arguments
+    /// * `progress` - How far through the iteration's generations the run is, from 0 to 1.
+    /// * `player_scale` - The mutation scale of the child being mutated.
+    pub fn scale(&self, progress: f64, player_scale: f64) -> f64 {
+        match self.schedule {
+            RateSchedule::Constant => 1.0,
+            RateSchedule::Linear => 1.0 + (self.end_scale - 1.0) * progress,
+            RateSchedule::SelfAdaptive => player_scale
+        }
+    }
+    /// Mutates a Screener.
+    ///
+    /// # Arguments
+    /// * `screener` - The Screener to mutate.
+    /// * `scale` - The factor every rate is multiplied by.
+    /// * `l_limits` - The lowest value of each field.
+    /// * `u_limits` - The highest value of each field.
+    /// * `fields` - The banned and required fields.
+    /// * `percentile_gap` - The percentile gap that new thresholds are rounded to.
+    ///
+    /// # Remarks
+    /// A field that was never observed (whose lower limit is above its upper limit) is never
+    /// turned on.
+    pub fn mutate<T: DataTrait>(&self, screener: &Screener<T>, scale: f64, (l_limits, u_limits): (&[T], &[T]), fields: &FieldSelection, percentile_gap: usize) -> Screener<T> {
+        let mut rng = rand::thread_rng();
+        let len = screener.len() as f64;
+        let mut screen = match self.threshold {
+            ThresholdMutation::Lazy => screener.lazy_mutate(self.threshold_rate * scale, percentile_gap).screen,
+            ThresholdMutation::Gaussian => {
+                screener.iter().enumerate().map(|(i, (threshold, used, rule))| {
+                    let (l, u) = (l_limits[i], u_limits[i]);
+                    let mut new_threshold = *threshold;
+                    if (l < u) && (rng.gen_range(0.0, 1.0) < self.threshold_rate * scale / len) {
+                        let (l_float, u_float) = (l.to_f64().unwrap(), u.to_f64().unwrap());
+                        let step = Normal::new(0.0, self.sigma * (u_float - l_float)).sample(&mut rng);
+                        new_threshold = T::from((threshold.to_f64().unwrap() + step).clamp(l_float, u_float)).unwrap().round(percentile_gap);
+                    }
+                    (new_threshold, *used, rule.clone())
+                }).collect()
+            }
+        };
+        let observed = |i: usize| l_limits[i] <= u_limits[i];
+        for (i, (_, used, rule)) in screen.iter_mut().enumerate() {
+            if rng.gen_range(0.0, 1.0) < self.toggle_rate * scale / len {
+                if *used {
+                    *used = fields.required_indicies.contains(&i);
+                } else {
+                    *used = observed(i) & !fields.banned_indicies.contains(&i);
+                }
+            }
+            if *used && (rng.gen_range(0.0, 1.0) < self.flip_rate * scale / len) {
+                *rule = match rule {
+                    Rule::Lt => Rule::Gt,
+                    Rule::Gt => Rule::Lt
+                };
+            }
+        }
+        if rng.gen_range(0.0, 1.0) < self.swap_rate * scale {
+            let from = (0..screen.len()).filter(|i| screen[*i].1 & !fields.required_indicies.contains(i)).collect::<Vec<usize>>();
+            let to = (0..screen.len()).filter(|i| !screen[*i].1 & observed(*i) & !fields.banned_indicies.contains(i)).collect::<Vec<usize>>();
+            if !from.is_empty() & !to.is_empty() {
+                let (from, to) = (from[rng.gen_range(0, from.len())], to[rng.gen_range(0, to.len())]);
+                let (l, u) = (l_limits[to], u_limits[to]);
+                let threshold = if l < u {rng.gen_range(l, u).round(percentile_gap)} else {l};
+                screen[from].1 = false;
+                screen[to] = (threshold, true, screen[from].2.clone());
+            }
+        }
+        Screener {
+            screen: screen
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> MutationSettings {
+        MutationSettings {
+            threshold: ThresholdMutation::Gaussian,
+            threshold_rate: 0.0,
+            toggle_rate: 0.0,
+            ..MutationSettings::new_default()
+        }
+    }
+
+    fn fields(banned: Vec<usize>, required: Vec<usize>) -> FieldSelection {
+        FieldSelection {
+            banned_indicies: banned,
+            required_indicies: required,
+            index_of_value: 0
+        }
+    }
+
+    fn screener(used: &[bool]) -> Screener<f64> {
+        Screener {
+            screen: used.iter().map(|used| (5.0, *used, Rule::Lt)).collect()
+        }
+    }
+
+    #[test]
+    fn schedules_scale_the_rates() {
+        let mut settings = settings();
+        assert_eq!(settings.scale(0.5, 3.0), 1.0);
+        assert_eq!(settings.child_scale(3.0), 3.0);
+        settings.schedule = RateSchedule::Linear;
+        settings.end_scale = 0.2;
+        assert_eq!(settings.scale(0.0, 3.0), 1.0);
+        assert!((settings.scale(1.0, 3.0) - 0.2).abs() < 1e-9);
+        settings.schedule = RateSchedule::SelfAdaptive;
+        assert_eq!(settings.scale(0.5, 3.0), 3.0);
+        let child_scale = settings.child_scale(3.0);
+        assert!((0.01..=100.0).contains(&child_scale));
+    }
+
+    #[test]
+    fn gaussian_thresholds_stay_within_the_limits() {
+        let settings = MutationSettings {
+            threshold_rate: 4.0,
+            sigma: 10.0,
+            ..settings()
+        };
+        let (l_limits, u_limits) = (vec![0.0; 4], vec![10.0; 4]);
+        for _ in 0..50 {
+            let child = settings.mutate(&screener(&[true; 4]), 1.0, (&l_limits, &u_limits), &fields(Vec::new(), Vec::new()), 1);
+            assert!(child.iter().all(|(threshold, _, _)| (0.0..=10.0).contains(threshold)));
+        }
+    }
+
+    #[test]
+    fn toggles_respect_banned_required_and_unobserved_fields() {
+        // A toggle rate of the Screener's length toggles every field.
+        let settings = MutationSettings {
+            toggle_rate: 4.0,
+            ..settings()
+        };
+        let (l_limits, u_limits) = (vec![0.0, 0.0, 0.0, 1.0], vec![10.0, 10.0, 10.0, 0.0]);
+        let child = settings.mutate(&screener(&[true, true, false, false]), 1.0, (&l_limits, &u_limits), &fields(vec![2], vec![0]), 1);
+        assert_eq!(child.iter().map(|(_, used, _)| *used).collect::<Vec<bool>>(), vec![true, false, false, false]);
+    }
+
+    #[test]
+    fn swaps_move_a_rule_to_an_unused_field() {
+        let settings = MutationSettings {
+            swap_rate: 1.0,
+            ..settings()
+        };
+        let (l_limits, u_limits) = (vec![0.0; 3], vec![10.0; 3]);
+        let child = settings.mutate(&screener(&[true, true, false]), 1.0, (&l_limits, &u_limits), &fields(Vec::new(), vec![0]), 1);
+        assert_eq!(child.iter().map(|(_, used, _)| *used).collect::<Vec<bool>>(), vec![true, false, true]);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+
+use crate::data_trait::DataTrait;
+use crate::error::Error;
+use crate::screener::{Rule, Screener};
+
+/// The most players the diversity of a population is measured over.
+pub static DIVERSITY_SAMPLE_SIZE: usize = 100;
+
+/// How diversity is maintained in the population.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct NichingSettings {
+    /// Divide each player's fitness by the number of players in its niche before selection.
+    pub sharing: bool,
+    /// Children replace the parent they most resemble, and only if they're fitter.
+    pub crowding: bool,
+    /// The distance within which two players share fitness, and the radius of a niche.
+    pub sigma: f64,
+    /// The shape of the sharing function, 1 being linear.
+    pub alpha: f64,
+    /// How much of the distance comes from the used fields, the rest coming from the thresholds.
+    pub field_weight: f64
+}
+
+/// The diversity of a population.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Diversity {
+    pub mean_distance: f64,
+    pub niches: usize
+}
+
+impl fmt::Display for NichingSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NichingSettings[sharing: {}, crowding: {}, sigma: {}, alpha: {}, field_weight: {}]", self.sharing, self.crowding, self.sigma, self.alpha, self.field_weight)
+    }
+}
+
+impl fmt::Display for Diversity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mean Pairwise Distance: {:.3}, Niches: {}", self.mean_distance, self.niches)
+    }
+}
+
+impl NichingSettings {
+    /// The default settings: no sharing or crowding, with a niche radius of 0.3.
+    pub fn new_default() -> NichingSettings {
+        NichingSettings {
+            sharing: false,
+            crowding: false,
+            sigma: 0.3,
+            alpha: 1.0,
+            field_weight: 0.5
+        }
+    }
+    /// Returns an error if the niche radius isn't a positive number, since every niche count
+    /// would be zero and sharing would divide by it.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.sigma.is_finite() && (self.sigma > 0.0) {
+            Ok(())
+        } else {
+            Err(Error::Config(format!("the niche radius must be a positive number, not {}.", self.sigma)))
+        }
+    }
+    /// The distance between two Screeners, between 0 and 1.
+    ///
+    /// # Arguments
+    /// * `a` - A Screener.
+    /// * `b` - Another Screener.
+    /// * `spans` - The range of each field, used to scale threshold differences.
+    ///
+    /// # Remarks
+    /// The field distance is the Jaccard distance between the sets of used fields. The threshold
+    /// distance is the mean over the fields both use of the scaled threshold difference, or 1
+    /// where the rules differ, and is 1 if no field is shared. Two Screeners that use no fields
+    /// are identical.
+    pub fn distance<T: DataTrait>(&self, a: &Screener<T>, b: &Screener<T>, spans: &[f64]) -> f64 {
+        let mut union = 0;
+        let mut shared = 0;
+        let mut threshold_distance = 0.0;
+        for (((a_threshold, a_used, a_rule), (b_threshold, b_used, b_rule)), span) in a.iter().zip(b.iter()).zip(spans.iter()) {
+            if *a_used | *b_used {
+                union += 1;
+            }
+            if *a_used & *b_used {
+                shared += 1;
+                threshold_distance += match (a_rule, b_rule) {
+                    (Rule::Lt, Rule::Lt) | (Rule::Gt, Rule::Gt) => {
+                        if *span > 0.0 {
+                            ((a_threshold.to_f64().unwrap() - b_threshold.to_f64().unwrap()).abs() / span).min(1.0)
+                        } else {
+                            0.0
+                        }
+                    },
+                    _ => 1.0
+                };
+            }
+        }
+        if union == 0 {
+            return 0.0;
+        }
+        let field_distance = 1.0 - (shared as f64) / (union as f64);
+        let threshold_distance = if shared == 0 {1.0} else {threshold_distance / (shared as f64)};
+        self.field_weight * field_distance + (1.0 - self.field_weight) * threshold_distance
+    }
+    /// The distances between every pair of Screeners. This is quadratic in the number of
+    /// Screeners, so it's only computed for each island when fitness is shared.
+    ///
+    /// # Arguments
+    /// * `screeners` - The Screeners.
+    /// * `spans` - The range of each field.
+    pub fn distances<T: DataTrait>(&self, screeners: &[&Screener<T>], spans: &[f64]) -> Vec<Vec<f64>> {
+        let mut distances = vec![vec![0.0; screeners.len()]; screeners.len()];
+        for i in 0..screeners.len() {
+            for j in (i + 1)..screeners.len() {
+                let distance = self.distance(screeners[i], screeners[j], spans);
+                distances[i][j] = distance;
+                distances[j][i] = distance;
+            }
+        }
+        distances
+    }
+    /// Divides each fitness by the niche count of its player, the sum of the sharing function
+    /// over every player.
+    ///
+    /// # Arguments
+    /// * `fitness` - The raw fitness of each player.
+    /// * `distances` - The distances between the players.
+    pub fn shared_fitness(&self, fitness: &[f64], distances: &[Vec<f64>]) -> Vec<f64> {
+        fitness.iter().zip(distances.iter()).map(|(raw, row)| {
+            let niche_count = row.iter().fold(0.0, |acc, distance| {
+                if *distance < self.sigma {acc + 1.0 - (distance / self.sigma).powf(self.alpha)} else {acc}
+            });
+            raw / niche_count
+        }).collect()
+    }
+    /// Measures the diversity of a population.
+    ///
+    /// # Arguments
+    /// * `fitness` - The fitness of each player.
+    /// * `screeners` - The Screener of each player.
+    /// * `spans` - The range of each field.
+    ///
+    /// # Remarks
+    /// A population of more than DIVERSITY_SAMPLE_SIZE players is measured over that many players
+    /// spread evenly through it, so the mean distance is an estimate and the niches are those of
+    /// the sample. Niches are found greedily: in order of fitness, each player not within sigma of
+    /// an existing niche's centre becomes the centre of a new niche.
+    pub fn diversity<T: DataTrait>(&self, fitness: &[f64], screeners: &[&Screener<T>], spans: &[f64]) -> Diversity {
+        let sample = if fitness.len() <= DIVERSITY_SAMPLE_SIZE {
+            (0..fitness.len()).collect::<Vec<usize>>()
+        } else {
+            (0..DIVERSITY_SAMPLE_SIZE).map(|k| k * fitness.len() / DIVERSITY_SAMPLE_SIZE).collect()
+        };
+        let fitness = sample.iter().map(|i| fitness[*i]).collect::<Vec<f64>>();
+        let distances = self.distances(&sample.iter().map(|i| screeners[*i]).collect::<Vec<_>>(), spans);
+        let n = fitness.len();
+        let pairs = n * n.saturating_sub(1) / 2;
+        let total = distances.iter().enumerate().fold(0.0, |acc, (i, row)| acc + row[(i + 1)..].iter().sum::<f64>());
+        let mut order = (0..n).collect::<Vec<usize>>();
+        order.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+        let mut centres: Vec<usize> = Vec::new();
+        for i in order {
+            if centres.iter().all(|centre| distances[i][*centre] >= self.sigma) {
+                centres.push(i);
+            }
+        }
+        Diversity {
+            mean_distance: if pairs != 0 {total / (pairs as f64)} else {0.0},
+            niches: centres.len()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screener(screen: Vec<(f64, bool, Rule)>) -> Screener<f64> {
+        Screener {
+            screen: screen
+        }
+    }
+
+    #[test]
+    fn distance_weighs_fields_and_thresholds() {
+        let niching = NichingSettings::new_default();
+        let spans = [10.0, 10.0];
+        let a = screener(vec![(2.0, true, Rule::Gt), (0.0, false, Rule::Gt)]);
+        let b = screener(vec![(7.0, true, Rule::Gt), (0.0, false, Rule::Gt)]);
+        let c = screener(vec![(2.0, true, Rule::Gt), (5.0, true, Rule::Lt)]);
+        assert_eq!(niching.distance(&a, &a, &spans), 0.0);
+        assert_eq!(niching.distance(&a, &b, &spans), 0.25);
+        assert_eq!(niching.distance(&a, &c, &spans), 0.25);
+        assert_eq!(niching.distance(&screener(vec![(2.0, false, Rule::Gt)]), &screener(vec![(9.0, false, Rule::Lt)]), &spans), 0.0);
+    }
+
+    #[test]
+    fn crowded_players_share_their_fitness() {
+        let niching = NichingSettings::new_default();
+        let distances = vec![vec![0.0, 0.15, 1.0], vec![0.15, 0.0, 1.0], vec![1.0, 1.0, 0.0]];
+        assert_eq!(niching.shared_fitness(&[3.0, 3.0, 3.0], &distances), vec![2.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn a_niche_radius_of_zero_is_rejected() {
+        let mut niching = NichingSettings::new_default();
+        assert!(niching.check().is_ok());
+        niching.sigma = 0.0;
+        assert!(matches!(niching.check(), Err(Error::Config(_))));
+        niching.sigma = f64::NAN;
+        assert!(niching.check().is_err());
+    }
+
+    #[test]
+    fn niches_are_counted_from_the_fittest() {
+        let niching = NichingSettings::new_default();
+        let screeners = [0.0, 1.0, 9.0].iter().map(|threshold| screener(vec![(*threshold, true, Rule::Gt)])).collect::<Vec<_>>();
+        let diversity = niching.diversity(&[1.0, 2.0, 3.0], &screeners.iter().collect::<Vec<_>>(), &[10.0]);
+        assert_eq!(diversity.niches, 2);
+        assert_eq!((diversity.mean_distance * 1000.0).round(), 300.0);
+    }
+}
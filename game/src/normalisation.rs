@@ -0,0 +1,160 @@
+use std::fmt;
+
+use crate::error::Error;
+use crate::percentile::{midrank, quantile, PercentileMethod};
+
+/// The space that the raw Quarters<f64> data is transformed into before the Screeners are
+/// evolved. Screener thresholds are always expressed in the chosen space.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum Normalisation {
+    /// Bucketed percentiles with a fixed gap that divides 100 (the original transform).
+    Percentile,
+    /// The exact fractional rank of each value within its quarter, as a percentage in (0, 100].
+    FractionalRank,
+    /// Cross-sectional z-scores, after winsorising the given percentage of each tail.
+    ZScore(f64),
+    /// A signed log transform, sign(x) * ln(1 + |x|).
+    Log,
+    /// Raw values centred on the quarter median and scaled by the interquartile range.
+    RobustScale
+}
+
+impl fmt::Display for Normalisation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Normalisation::Percentile => write!(f, "Normalisation[Percentile]"),
+            Normalisation::FractionalRank => write!(f, "Normalisation[FractionalRank]"),
+            Normalisation::ZScore(winsor_percent) => write!(f, "Normalisation[ZScore, winsor_percent: {}]", winsor_percent),
+            Normalisation::Log => write!(f, "Normalisation[Log]"),
+            Normalisation::RobustScale => write!(f, "Normalisation[RobustScale]")
+        }
+    }
+}
+
+impl Normalisation {
+    /// Parse a Normalisation from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "percentile", "rank", "zscore", "log" or "robust".
+    /// * `winsor_percent` - The percentage of each tail to winsorise, only used by "zscore".
+    pub fn from_name(name: &str, winsor_percent: f64) -> Option<Normalisation> {
+        match name {
+            "percentile" => Some(Normalisation::Percentile),
+            "rank" => Some(Normalisation::FractionalRank),
+            "zscore" => Some(Normalisation::ZScore(winsor_percent)),
+            "log" => Some(Normalisation::Log),
+            "robust" => Some(Normalisation::RobustScale),
+            _ => None
+        }
+    }
+    /// Returns true if this Normalisation produces the bucketed Quarters<usize> space.
+    pub fn is_percentile(&self) -> bool {
+        matches!(self, Normalisation::Percentile)
+    }
+    /// A short label for this Normalisation, used when naming output files.
+    ///
+    /// # Arguments
+    /// * `percentile_gap` - The percentile gap, only used by Normalisation::Percentile.
+    pub fn label(&self, percentile_gap: usize) -> String {
+        match self {
+            Normalisation::Percentile => format!("perc{}", percentile_gap),
+            Normalisation::FractionalRank => "rank".to_string(),
+            Normalisation::ZScore(winsor_percent) => format!("zscore{}", winsor_percent),
+            Normalisation::Log => "log".to_string(),
+            Normalisation::RobustScale => "robust".to_string()
+        }
+    }
+    /// Transform every value of one field in one quarter into the normalised space.
+    ///
+    /// # Arguments
+    /// * `values` - The values of the field, in record order. None entries, and values that
+    ///   aren't finite like "NaN" in the data, become None.
+    ///
+    /// # Remarks
+    /// Normalisation::Percentile changes the data type, and is produced by
+    /// Quarters::create_percentile_quarters instead. Calling this with it returns an error.
+    pub fn transform(&self, values: &[Option<f64>]) -> Result<Vec<Option<f64>>, Error> {
+        let values = values.iter().map(|value| value.filter(|x| x.is_finite())).collect::<Vec<Option<f64>>>();
+        let mut sorted = values.iter().filter_map(|value| *value).collect::<Vec<f64>>();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        if sorted.is_empty() {
+            return Ok(values);
+        }
+        Ok(match self {
+            Normalisation::Percentile => return Err(Error::Config("percentile normalisation can't be applied as a float transform.".to_string())),
+            Normalisation::FractionalRank => {
+                values.iter().map(|value| value.map(|x| midrank(&sorted, x))).collect()
+            },
+            Normalisation::ZScore(winsor_percent) => {
+                let lower = quantile(&sorted, *winsor_percent, &PercentileMethod::Linear);
+                let upper = quantile(&sorted, 100.0 - *winsor_percent, &PercentileMethod::Linear);
+                let winsorised = sorted.iter().map(|x| x.max(lower).min(upper)).collect::<Vec<f64>>();
+                let n = winsorised.len() as f64;
+                let mean = winsorised.iter().sum::<f64>() / n;
+                let sd = (winsorised.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+                values.iter().map(|value| value.map(|x| {
+                    if sd > 0.0 {(x.max(lower).min(upper) - mean) / sd} else {0.0}
+                })).collect()
+            },
+            Normalisation::Log => {
+                values.iter().map(|value| value.map(|x| x.signum() * x.abs().ln_1p())).collect()
+            },
+            Normalisation::RobustScale => {
+                let median = quantile(&sorted, 50.0, &PercentileMethod::Linear);
+                let iqr = quantile(&sorted, 75.0, &PercentileMethod::Linear) - quantile(&sorted, 25.0, &PercentileMethod::Linear);
+                values.iter().map(|value| value.map(|x| {
+                    if iqr > 0.0 {(x - median) / iqr} else {x - median}
+                })).collect()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(values: Vec<Option<f64>>) -> Vec<Option<f64>> {
+        values.iter().map(|value| value.map(|x| (x * 1000.0).round() / 1000.0)).collect()
+    }
+
+    #[test]
+    fn fractional_ranks_share_ties() {
+        let ranks = Normalisation::FractionalRank.transform(&[Some(10.0), None, Some(30.0), Some(20.0), Some(20.0)]).unwrap();
+        assert_eq!(ranks, vec![Some(25.0), None, Some(100.0), Some(62.5), Some(62.5)]);
+    }
+
+    #[test]
+    fn z_scores_are_winsorised() {
+        let scores = Normalisation::ZScore(25.0).transform(&[Some(1.0), Some(2.0), Some(3.0), Some(4.0), Some(100.0)]).unwrap();
+        assert_eq!(approx(scores), vec![Some(-1.118), Some(-1.118), Some(0.0), Some(1.118), Some(1.118)]);
+        assert_eq!(Normalisation::ZScore(0.0).transform(&[Some(5.0), Some(5.0)]).unwrap(), vec![Some(0.0), Some(0.0)]);
+    }
+
+    #[test]
+    fn logs_keep_their_sign() {
+        let e = std::f64::consts::E;
+        let logs = Normalisation::Log.transform(&[Some(1.0 - e), Some(0.0), Some(e - 1.0)]).unwrap();
+        assert_eq!(approx(logs), vec![Some(-1.0), Some(0.0), Some(1.0)]);
+    }
+
+    #[test]
+    fn robust_scaling_uses_the_median_and_interquartile_range() {
+        let scaled = Normalisation::RobustScale.transform(&[Some(1.0), Some(2.0), Some(3.0), Some(4.0), Some(5.0)]).unwrap();
+        assert_eq!(scaled, vec![Some(-1.0), Some(-0.5), Some(0.0), Some(0.5), Some(1.0)]);
+        assert_eq!(Normalisation::RobustScale.transform(&[Some(3.0), Some(3.0), Some(3.0), Some(3.0), Some(3.0), Some(10.0)]).unwrap()[5], Some(7.0));
+    }
+
+    #[test]
+    fn values_that_are_not_finite_are_left_out() {
+        let ranks = Normalisation::FractionalRank.transform(&[Some(f64::NAN), Some(1.0), Some(f64::INFINITY), Some(2.0)]).unwrap();
+        assert_eq!(ranks, vec![None, Some(50.0), None, Some(100.0)]);
+        assert_eq!(Normalisation::Log.transform(&[Some(f64::NAN)]).unwrap(), vec![None]);
+    }
+
+    #[test]
+    fn percentiles_are_not_a_float_transform() {
+        assert!(matches!(Normalisation::Percentile.transform(&[Some(1.0)]), Err(Error::Config(_))));
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+use crate::data_trait::DataTrait;
+use crate::hall_of_fame::HallOfFame;
+use crate::player::Player;
+use crate::statistics::GenerationStats;
+
+/// Receives the events of a Game as it runs. Every method does nothing by default, so an Observer
+/// only implements the events it needs.
+///
+/// # Remarks
+/// Observers are called on the thread running the Game, between generations, so they see the
+/// population as it was when the event happened and slow the Game down by however long they
+/// take.
+pub trait Observer<T: DataTrait>: Send + Sync {
+    /// The Game has started running.
+    ///
+    /// # Arguments
+    /// * `generation_max` - The max number of generations of each iteration.
+    /// * `iterations` - The number of iterations, the last being the final run.
+    fn run_started(&mut self, _generation_max: usize, _iterations: usize) {}
+    /// A generation has been run through the test data, before it was bred.
+    ///
+    /// # Arguments
+    /// * `stats` - The statistics of the generation.
+    fn generation_evaluated(&mut self, _stats: &GenerationStats) {}
+    /// A Player has beaten the best payoff so far of the current iteration.
+    ///
+    /// # Arguments
+    /// * `stats` - The statistics of the generation the Player was found in.
+    /// * `player` - The Player, after being run through the test data.
+    fn new_best(&mut self, _stats: &GenerationStats, _player: &Player<T>) {}
+    /// An iteration has finished its final run.
+    ///
+    /// # Arguments
+    /// * `iteration` - The number of the iteration.
+    /// * `stop_report` - Why the iteration stopped, if it trained.
+    /// * `best` - The best payoff of the final run, and the Player that made it, if any.
+    fn iteration_finished(&mut self, _iteration: usize, _stop_report: Option<&str>, _best: Option<(f64, &Player<T>)>) {}
+    /// The Game has finished running and has been saved.
+    ///
+    /// # Arguments
+    /// * `hall_of_fame` - The best distinct Screeners the Game evaluated.
+    fn run_finished(&mut self, _hall_of_fame: &HallOfFame<T>) {}
+}
+
+/// The Observers attached to a Game, in the order they were added.
+pub struct Observers<T: DataTrait> {
+    observers: Vec<Box<dyn Observer<T>>>
+}
+
+impl<T: DataTrait> fmt::Debug for Observers<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Observers[count: {}]", self.observers.len())
+    }
+}
+
+impl<T: DataTrait> Observers<T> {
+    /// Creates an empty list of Observers.
+    pub fn new_empty() -> Observers<T> {
+        Observers {
+            observers: Vec::new()
+        }
+    }
+    /// Adds an Observer, called after those already added.
+    pub fn push(&mut self, observer: Box<dyn Observer<T>>) {
+        self.observers.push(observer);
+    }
+    /// Passes an event to every Observer in order.
+    ///
+    /// # Arguments
+    /// * `event` - Calls the method of the event on an Observer.
+    pub fn notify<F: FnMut(&mut dyn Observer<T>)>(&mut self, mut event: F) {
+        for observer in self.observers.iter_mut() {
+            event(observer.as_mut());
+        }
+    }
+}
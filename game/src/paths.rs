@@ -0,0 +1,67 @@
+use std::{env, fmt, path::{Path, PathBuf}};
+
+/// The environment variables that the paths can be set with, in the order root, data, input and
+/// output. Command line flags override them.
+pub static PATH_VARIABLES: [&str; 4] = ["GAME_ROOT", "GAME_DATA", "GAME_INPUT", "GAME_OUTPUT"];
+
+/// Where the data is read from and the results are written to. Relative paths are relative to the
+/// root, and a relative root is relative to the working directory.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct PathSettings {
+    pub root: PathBuf,
+    /// The directory of CSV files that the Quarters are loaded from.
+    pub data: PathBuf,
+    /// The file a Screener is read from when a test isn't given one.
+    pub input: PathBuf,
+    /// The directory each run's output and statistics files are written to.
+    pub output: PathBuf
+}
+
+impl fmt::Display for PathSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PathSettings[root: {:?}, data: {:?}, input: {:?}, output: {:?}]", self.root, self.data, self.input, self.output)
+    }
+}
+
+impl PathSettings {
+    /// The default paths: the working directory is the root, with everything in its test-data
+    /// directory.
+    pub fn new_default() -> PathSettings {
+        PathSettings {
+            root: PathBuf::from("."),
+            data: PathBuf::from("test-data/TrimmedUnitedData"),
+            input: PathBuf::from("test-data/input.txt"),
+            output: PathBuf::from("test-data")
+        }
+    }
+    /// The default paths, overridden by any of the PATH_VARIABLES that are set.
+    pub fn from_environment() -> PathSettings {
+        let mut paths = PathSettings::new_default();
+        for (variable, path) in PATH_VARIABLES.iter().zip([&mut paths.root, &mut paths.data, &mut paths.input, &mut paths.output].iter_mut()) {
+            if let Some(value) = env::var_os(variable) {
+                **path = PathBuf::from(value);
+            }
+        }
+        paths
+    }
+    /// Returns a path relative to the root, or the path itself if it's absolute.
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.root.join(path)
+    }
+    /// Returns the data directory.
+    pub fn data_directory(&self) -> PathBuf {
+        self.resolve(&self.data)
+    }
+    /// Returns the input file.
+    pub fn input_file(&self) -> PathBuf {
+        self.resolve(&self.input)
+    }
+    /// Returns the path of an output file.
+    ///
+    /// # Arguments
+    /// * `file_name` - The name of the file within the output directory.
+    pub fn output_file(&self, file_name: &str) -> PathBuf {
+        self.resolve(&self.output).join(file_name)
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+
+use crate::error::Error;
+
+/// How the percentile of a value is computed within a quarter.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum PercentileMethod {
+    /// Bucket boundaries are the values at rank ceil(p * n), and a value falls in the first
+    /// bucket whose boundary it doesn't exceed.
+    NearestRank,
+    /// Bucket boundaries are linearly interpolated between the closest ranks.
+    Linear,
+    /// A value's percentile is its midrank, so tied values share the bucket of their average
+    /// rank rather than all falling into the lowest bucket they touch.
+    Midrank
+}
+
+/// The settings used to turn a quarter of raw values into percentiles.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct PercentileSettings {
+    pub method: PercentileMethod,
+    pub min_samples: usize
+}
+
+/// The sorted observations of one field in one quarter, with the bucket boundaries for a
+/// given percentile gap.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct PercentileTable {
+    sorted: Vec<f64>,
+    boundaries: Vec<f64>,
+    method: PercentileMethod,
+    gap: usize
+}
+
+impl fmt::Display for PercentileMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PercentileMethod::NearestRank => write!(f, "NearestRank"),
+            PercentileMethod::Linear => write!(f, "Linear"),
+            PercentileMethod::Midrank => write!(f, "Midrank")
+        }
+    }
+}
+
+impl fmt::Display for PercentileSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PercentileSettings[method: {}, min_samples: {}]", self.method, self.min_samples)
+    }
+}
+
+impl PercentileMethod {
+    /// Parse a PercentileMethod from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "nearest", "linear" or "midrank".
+    pub fn from_name(name: &str) -> Option<PercentileMethod> {
+        match name {
+            "nearest" => Some(PercentileMethod::NearestRank),
+            "linear" => Some(PercentileMethod::Linear),
+            "midrank" => Some(PercentileMethod::Midrank),
+            _ => None
+        }
+    }
+}
+
+impl PercentileSettings {
+    /// The default settings: midranks, and at least 10 observations per quarter.
+    ///
+    /// # Remarks
+    /// Before these settings, a value was bucketed by the observations at index floor(p * n),
+    /// and every quarter with data had percentiles. NearestRank with a min_samples of 1 is the
+    /// closest to that.
+    pub fn new_default() -> PercentileSettings {
+        PercentileSettings {
+            method: PercentileMethod::Midrank,
+            min_samples: 10
+        }
+    }
+}
+
+impl PercentileTable {
+    /// Creates a PercentileTable from the observations of one field in one quarter.
+    ///
+    /// # Arguments
+    /// * `sorted` - The ascending sorted observations.
+    /// * `gap` - The distance between adjacent percentiles. This number must evenly divide 100.
+    /// * `settings` - The method and minimum sample size to use.
+    ///
+    /// # Remarks
+    /// Returns None if there are fewer than `settings.min_samples` observations (or none at
+    /// all), in which case no percentile is meaningful for this quarter. Returns an error if the
+    /// gap is zero or doesn't evenly divide 100.
+    pub fn new(sorted: Vec<f64>, gap: usize, settings: &PercentileSettings) -> Result<Option<PercentileTable>, Error> {
+        if (gap == 0) || (100 % gap != 0) {
+            return Err(Error::Config(format!("the percentile gap {} doesn't evenly divide 100.", gap)));
+        }
+        if sorted.is_empty() || (sorted.len() < settings.min_samples) {
+            return Ok(None);
+        }
+        let boundaries = match settings.method {
+            PercentileMethod::Midrank => Vec::new(),
+            _ => (1..(100 / gap)).map(|k| quantile(&sorted, (k * gap) as f64, &settings.method)).collect()
+        };
+        Ok(Some(PercentileTable {
+            sorted: sorted,
+            boundaries: boundaries,
+            method: settings.method.clone(),
+            gap: gap
+        }))
+    }
+    /// Returns the percentile bucket of a value, a multiple of the gap in [gap, 100].
+    ///
+    /// # Arguments
+    /// * `value` - The value to place.
+    pub fn bucket(&self, value: f64) -> usize {
+        match self.method {
+            PercentileMethod::Midrank => {
+                let percent = midrank(&self.sorted, value);
+                let bucket = ((percent / (self.gap as f64)).ceil() as usize) * self.gap;
+                bucket.clamp(self.gap, 100)
+            },
+            _ => {
+                match self.boundaries.iter().position(|boundary| value <= *boundary) {
+                    Some(k) => (k + 1) * self.gap,
+                    None => 100
+                }
+            }
+        }
+    }
+}
+
+/// The quantile of a non-empty sorted slice.
+///
+/// # Arguments
+/// * `sorted` - The ascending sorted observations.
+/// * `percent` - The percentile requested, in [0, 100].
+/// * `method` - NearestRank picks an observation, anything else interpolates linearly.
+pub fn quantile(sorted: &[f64], percent: f64, method: &PercentileMethod) -> f64 {
+    let p = percent.clamp(0.0, 100.0) / 100.0;
+    match method {
+        PercentileMethod::NearestRank => {
+            let rank = (p * (sorted.len() as f64)).ceil() as usize;
+            sorted[rank.max(1) - 1]
+        },
+        _ => {
+            let position = p * ((sorted.len() - 1) as f64);
+            let lower = position.floor() as usize;
+            let upper = position.ceil() as usize;
+            sorted[lower] + (sorted[upper] - sorted[lower]) * (position - (lower as f64))
+        }
+    }
+}
+
+/// The midrank of a value within a non-empty sorted slice, as a percentage in (0, 100]. Tied
+/// values share the average of the ranks they span.
+///
+/// # Arguments
+/// * `sorted` - The ascending sorted observations.
+/// * `value` - The value to rank.
+pub fn midrank(sorted: &[f64], value: f64) -> f64 {
+    let below = sorted.partition_point(|x| *x < value);
+    let not_above = sorted.partition_point(|x| *x <= value);
+    let equal = not_above - below;
+    100.0 * ((below as f64) + ((equal as f64) + 1.0) / 2.0) / (sorted.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_to_ten() -> Vec<f64> {
+        (1..=10).map(|x| x as f64).collect()
+    }
+
+    fn settings(method: PercentileMethod, min_samples: usize) -> PercentileSettings {
+        PercentileSettings {
+            method: method,
+            min_samples: min_samples
+        }
+    }
+
+    #[test]
+    fn nearest_rank_quantiles() {
+        let sorted = one_to_ten();
+        assert_eq!(quantile(&sorted, 0.0, &PercentileMethod::NearestRank), 1.0);
+        assert_eq!(quantile(&sorted, 10.0, &PercentileMethod::NearestRank), 1.0);
+        assert_eq!(quantile(&sorted, 25.0, &PercentileMethod::NearestRank), 3.0);
+        assert_eq!(quantile(&sorted, 50.0, &PercentileMethod::NearestRank), 5.0);
+        assert_eq!(quantile(&sorted, 100.0, &PercentileMethod::NearestRank), 10.0);
+        let ties = [0.0, 0.0, 0.0, 0.0, 5.0];
+        assert_eq!(quantile(&ties, 50.0, &PercentileMethod::NearestRank), 0.0);
+        assert_eq!(quantile(&ties, 90.0, &PercentileMethod::NearestRank), 5.0);
+    }
+
+    #[test]
+    fn linear_quantiles() {
+        let sorted = one_to_ten();
+        assert_eq!(quantile(&sorted, 0.0, &PercentileMethod::Linear), 1.0);
+        assert_eq!(quantile(&sorted, 25.0, &PercentileMethod::Linear), 3.25);
+        assert_eq!(quantile(&sorted, 50.0, &PercentileMethod::Linear), 5.5);
+        assert_eq!(quantile(&sorted, 100.0, &PercentileMethod::Linear), 10.0);
+        let ties = [0.0, 0.0, 0.0, 0.0, 5.0];
+        assert_eq!(quantile(&ties, 50.0, &PercentileMethod::Linear), 0.0);
+        assert!((quantile(&ties, 90.0, &PercentileMethod::Linear) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn midranks() {
+        let sorted = one_to_ten();
+        assert_eq!(midrank(&sorted, 1.0), 10.0);
+        assert_eq!(midrank(&sorted, 5.0), 50.0);
+        assert_eq!(midrank(&sorted, 10.0), 100.0);
+        assert_eq!(midrank(&sorted, 0.0), 5.0);
+        let ties = [0.0, 0.0, 0.0, 0.0, 5.0];
+        assert_eq!(midrank(&ties, 0.0), 50.0);
+        assert_eq!(midrank(&ties, 2.0), 90.0);
+        assert_eq!(midrank(&ties, 5.0), 100.0);
+    }
+
+    #[test]
+    fn the_gap_must_divide_100() {
+        for gap in [0, 3, 30, 101] {
+            assert!(matches!(PercentileTable::new(one_to_ten(), gap, &settings(PercentileMethod::Linear, 1)), Err(Error::Config(_))));
+        }
+        assert!(PercentileTable::new(one_to_ten(), 25, &settings(PercentileMethod::Linear, 1)).unwrap().is_some());
+        assert!(PercentileTable::new(Vec::new(), 0, &settings(PercentileMethod::Midrank, 1)).is_err());
+    }
+
+    #[test]
+    fn too_few_samples_have_no_table() {
+        let sorted = one_to_ten();
+        assert!(PercentileTable::new(Vec::new(), 10, &settings(PercentileMethod::Midrank, 0)).unwrap().is_none());
+        assert!(PercentileTable::new(sorted[..9].to_vec(), 10, &settings(PercentileMethod::Midrank, 10)).unwrap().is_none());
+        assert!(PercentileTable::new(sorted.clone(), 10, &settings(PercentileMethod::Midrank, 10)).unwrap().is_some());
+        assert!(PercentileTable::new(sorted, 10, &settings(PercentileMethod::NearestRank, 11)).unwrap().is_none());
+    }
+
+    #[test]
+    fn values_on_a_boundary_fall_in_its_bucket() {
+        let nearest = PercentileTable::new(one_to_ten(), 10, &settings(PercentileMethod::NearestRank, 1)).unwrap().unwrap();
+        assert_eq!(nearest.bucket(0.5), 10);
+        assert_eq!(nearest.bucket(1.0), 10);
+        assert_eq!(nearest.bucket(3.0), 30);
+        assert_eq!(nearest.bucket(3.5), 40);
+        assert_eq!(nearest.bucket(9.0), 90);
+        assert_eq!(nearest.bucket(10.0), 100);
+        let linear = PercentileTable::new((1..=5).map(|x| x as f64).collect(), 25, &settings(PercentileMethod::Linear, 1)).unwrap().unwrap();
+        assert_eq!(linear.bucket(2.0), 25);
+        assert_eq!(linear.bucket(2.5), 50);
+        assert_eq!(linear.bucket(4.0), 75);
+        assert_eq!(linear.bucket(4.5), 100);
+        let midrank = PercentileTable::new(one_to_ten(), 10, &settings(PercentileMethod::Midrank, 1)).unwrap().unwrap();
+        assert_eq!(midrank.bucket(0.0), 10);
+        assert_eq!(midrank.bucket(5.0), 50);
+        assert_eq!(midrank.bucket(6.0), 60);
+        assert_eq!(midrank.bucket(10.0), 100);
+        let ties = PercentileTable::new(vec![0.0, 0.0, 0.0, 0.0, 5.0], 10, &settings(PercentileMethod::Midrank, 1)).unwrap().unwrap();
+        assert_eq!(ties.bucket(0.0), 50);
+        assert_eq!(ties.bucket(5.0), 100);
+    }
+}
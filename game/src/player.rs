@@ -1,7 +1,11 @@
 use std::fmt;
 
+use crate::columnar::Bitmap;
+use crate::crossover::Crossover;
 use crate::data_trait::DataTrait;
-use crate::data_record::DataRecord;
+use crate::data_record::StockID;
+use crate::fields::FieldSelection;
+use crate::mutation::MutationSettings;
 use crate::screener::Screener;
 use crate::screener::Rule;
 use crate::quarters::Quarters;
@@ -12,8 +16,11 @@ pub struct Player<T: DataTrait> {
     pub strategy: Screener<T>,
     pub spend: f64,
     pub spend_return: f64,
-    pub stocks_sold: Vec<(f64, f64, DataRecord<T>)>,
-    pub stocks_purchased: Vec<(f64, DataRecord<T>)>
+    pub stocks_sold: Vec<(f64, f64, StockID)>,
+    pub stocks_purchased: Vec<(f64, StockID)>,
+    pub selection: Bitmap,
+    /// The factor this Player's mutation rates are multiplied by, with self-adaptive rates.
+    pub mutation_scale: f64
 }
 
 impl<T: DataTrait> fmt::Display for Player<T> {
@@ -28,16 +35,20 @@ impl<T: DataTrait> Player<T> {
     /// # Arguments
     /// * `l_limits` - The lower limits for each element of the strategy Screener.
     /// * `r_limits` - The upper limits for each element of the strategy Screener.
+    /// * `banned_indicies` - The fields that the strategy can never use.
+    /// * `required_indicies` - The fields that the strategy must always use.
     ///
     /// # Remarks
     /// See Screener::new_uniform_random() documentation.
-    pub fn new_uniform_random((l_limits, r_limits): (&Vec<T>, &Vec<T>), banned_indicies: &Vec<usize>, percentile_gap: usize) -> Player<T> {
+    pub fn new_uniform_random((l_limits, r_limits): (&Vec<T>, &Vec<T>), banned_indicies: &[usize], required_indicies: &[usize], percentile_gap: usize) -> Player<T> {
         Player {
-            strategy: Screener::new_uniform_random((l_limits, r_limits), banned_indicies, percentile_gap),
+            strategy: Screener::new_uniform_random((l_limits, r_limits), banned_indicies, required_indicies, percentile_gap),
             spend: 0.0,
             spend_return: 0.0,
             stocks_sold: Vec::new(),
             stocks_purchased: Vec::new(),
+            selection: Bitmap::new(0, false),
+            mutation_scale: 1.0
         }
     }
     ///
@@ -48,50 +59,62 @@ impl<T: DataTrait> Player<T> {
             spend_return: 0.0,
             stocks_sold: Vec::new(),
             stocks_purchased: Vec::new(),
+            selection: Bitmap::new(0, false),
+            mutation_scale: 1.0
         }
     }
     /// Resets the player to have payoff 0, empty stocks vectors, and soft resets the strategies.
+    /// The stocks vectors keep their allocations.
     pub fn soft_reset(&mut self) {
         self.spend = 0.0;
         self.spend_return = 0.0;
-        self.stocks_sold = Vec::new();
-        self.stocks_purchased = Vec::new();
+        self.stocks_sold.clear();
+        self.stocks_purchased.clear();
     }
-    /// Perform a uniform crossover of two Players.
+    /// Perform a crossover of two Players.
     ///
     /// # Arguments
     /// * `player` - The Player object to be crossed with.
+    /// * `crossover` - The crossover operator applied to the strategies.
     ///
     /// # Remarks
     /// The resultant Player is new, and therefore isn't in the memory location of either of
     /// the two that constructed it. This allows the reuse of the Players that construct this
-    /// crossover. The payoff and stocks_purchased entries are reset. The fields_used entry has
-    /// it's elements picked randomly from either player.
-    pub fn dumb_crossover(&self, player: &Player<T>, percentile_gap: usize) -> Player<T> {
+    /// crossover. The payoff and stocks_purchased entries are reset, and the mutation scale is
+    /// the mean of the parents' scales.
+    pub fn crossover(&self, player: &Player<T>, crossover: &Crossover, percentile_gap: usize) -> Player<T> {
         Player {
-            strategy: self.strategy.dumb_crossover(&player.strategy, percentile_gap),
+            strategy: crossover.cross(&self.strategy, &player.strategy, percentile_gap),
             spend: 0.0,
             spend_return: 0.0,
             stocks_sold: Vec::new(),
-            stocks_purchased: Vec::new()
+            stocks_purchased: Vec::new(),
+            selection: Bitmap::new(0, false),
+            mutation_scale: (self.mutation_scale + player.mutation_scale) / 2.0
         }
     }
-    /// Perform a lazy mutation on the Player.
+    /// Perform a mutation on the Player.
     ///
     /// # Arguments
-    /// * `c` - The mutation constant to use for the mutation.
+    /// * `mutation` - The mutation operators and rates.
+    /// * `progress` - How far through the iteration's generations the run is, from 0 to 1.
+    /// * `limits` - The lower and upper limits of each field.
+    /// * `fields` - The banned and required fields.
     ///
     /// # Remarks
     /// This resultant Player is new, and therefore isn't in the memory location of the Player
     /// used to create it. This allows the reuse of the Player that constructs this mutation.
     /// The payoff and stocks_purchased entries are reset.
-    pub fn lazy_mutate(&self, c: f64, percentile_gap: usize) -> Player<T> {
+    pub fn mutate(&self, mutation: &MutationSettings, progress: f64, limits: (&[T], &[T]), fields: &FieldSelection, percentile_gap: usize) -> Player<T> {
+        let mutation_scale = mutation.child_scale(self.mutation_scale);
         Player {
-            strategy: self.strategy.lazy_mutate(c, percentile_gap),
+            strategy: mutation.mutate(&self.strategy, mutation.scale(progress, mutation_scale), limits, fields, percentile_gap),
             spend: 0.0,
             spend_return: 0.0,
             stocks_sold: Vec::new(),
-            stocks_purchased: Vec::new()
+            stocks_purchased: Vec::new(),
+            selection: Bitmap::new(0, false),
+            mutation_scale: mutation_scale
         }
     }
     /// Returns the percent gain of the Player over the whole timespan.
@@ -122,8 +145,4 @@ impl<T: DataTrait> Player<T> {
     pub fn format_screen<'a>(&'a self, quarters: &'a Quarters<T>) -> Vec<(&String, &Rule, &'a T)> {
         self.strategy.format_screen(quarters)
     }
-    ///
-    pub fn is_similar_to(&self, player: &Player<T>, ratio: f64) -> bool {
-        self.strategy.is_similar_to(&player.strategy, ratio)
-    }
 }
@@ -1,14 +1,23 @@
-use std::{fmt, slice::Iter};
+use std::{fmt, collections::HashMap, slice::Iter};
 
 use crate::data_trait::DataTrait;
-use crate::data_record::{DataRecord, StockID, TimeID};
-use crate::player::Player;
+use crate::data_record::{DataRecord, Frequency, TimeID};
+use crate::ticker::Ticker;
 
 #[derive(Debug)]
 #[derive(Clone)]
 pub struct Quarter<T: DataTrait> {
-    pub quarter_vector: Vec<DataRecord<T>>,
-    pub time_id: TimeID
+    quarter_vector: Vec<DataRecord<T>>,
+    pub time_id: TimeID,
+    index: HashMap<Ticker, usize>
+}
+
+/// A DataRecord of a Quarter, borrowed so that its values and iteration can be changed, but not
+/// the name that the Quarter indexes it by.
+pub struct RecordMut<'a, T: DataTrait> {
+    pub name: Ticker,
+    pub record: &'a mut Vec<Option<T>>,
+    pub iteration: &'a mut usize
 }
 
 impl<T: DataTrait> fmt::Display for Quarter<T> {
@@ -18,18 +27,38 @@ impl<T: DataTrait> fmt::Display for Quarter<T> {
 }
 
 impl<T: DataTrait> Quarter<T> {
+    /// Creates a Quarter from a vector of DataRecords, indexed by stock name.
+    ///
+    /// # Arguments
+    /// * `quarter_vector` - The DataRecords in the Quarter.
+    /// * `time_id` - The time that this Quarter represents.
+    pub fn new(quarter_vector: Vec<DataRecord<T>>, time_id: TimeID) -> Quarter<T> {
+        let mut quarter = Quarter {
+            quarter_vector: quarter_vector,
+            time_id: time_id,
+            index: HashMap::new()
+        };
+        quarter.rebuild_index();
+        quarter
+    }
     /// Creates a blank Quarter with a length of zero.
     ///
     /// # Arguments
     /// * `year` - The year that this Quarter is from.
     /// * `quarter` - The quarter that this Quarter represents.
     pub fn load_blank(year: i64, quarter: i64) -> Quarter<T> {
-        Quarter {
-            quarter_vector: Vec::new(),
-            time_id: TimeID {
-                year: year,
-                quarter: quarter
-            }
+        Quarter::new(Vec::new(), TimeID {
+            year: year,
+            period: quarter,
+            frequency: Frequency::Quarterly
+        })
+    }
+    /// Recomputes the map from stock name to position in the quarter_vector. If a name appears
+    /// more than once, the first appearance is indexed.
+    fn rebuild_index(&mut self) {
+        self.index = HashMap::with_capacity(self.quarter_vector.len());
+        for (i, stock) in self.quarter_vector.iter().enumerate() {
+            self.index.entry(stock.stock_id.name).or_insert(i);
         }
     }
     /// Returns the length of the quarter_vector field.
@@ -48,6 +77,7 @@ impl<T: DataTrait> Quarter<T> {
     /// # Arguments
     /// * `new_record` - The record to be pushed.
     pub fn push(&mut self, new_record: DataRecord<T>) {
+        self.index.entry(new_record.stock_id.name).or_insert(self.quarter_vector.len());
         self.quarter_vector.push(new_record);
     }
     /// Returns an iterator over references to the elements in the quarter_vector variable of the
@@ -55,82 +85,124 @@ impl<T: DataTrait> Quarter<T> {
     pub fn iter(&self) -> Iter<DataRecord<T>> {
         self.quarter_vector.iter()
     }
+    /// Returns an iterator over the DataRecords of the Quarter that can change their values and
+    /// iterations, but not their names or positions.
+    pub fn records_mut(&mut self) -> impl Iterator<Item = RecordMut<'_, T>> {
+        self.quarter_vector.iter_mut().map(|stock| RecordMut {
+            name: stock.stock_id.name,
+            record: &mut stock.record,
+            iteration: &mut stock.stock_id.iteration
+        })
+    }
     /// Removes the DataRecord in the index provided, and returns it.
     ///
     /// # Arguments
     /// * `index` - The index of the element to be removed and returned.
-    pub fn remove(&mut self, index: usize) -> DataRecord<T> {
-        self.quarter_vector.remove(index)
-    }
-    /// Assigns to a Player a vector of DataRecords that are piecewise strictly larger than that
-    /// Player's set strategy.
-    ///
-    /// # Arguments
-    /// * `player` - A Player struct.
     ///
     /// # Remarks
-    /// This function is overly convoluted.
-    pub fn select_for_player(&self, float_quarter: &Quarter<f64>, player: &mut Player<T>, index: usize, iteration: usize) {
-        // Buy from quarter
-        for (stock, stock_float) in self.iter().zip(float_quarter.iter()) {
-            if stock.is_satisfied_by(&player) & (stock.stock_id.iteration == iteration) {
-                player.stocks_purchased.push((stock_float.get(index).unwrap(), stock.clone()));
+    /// The positions of the later DataRecords are shifted down in place rather than reindexed, but
+    /// removing many DataRecords one at a time is still quadratic, so use retain for that.
+    pub fn remove(&mut self, index: usize) -> DataRecord<T> {
+        let removed = self.quarter_vector.remove(index);
+        let name = removed.stock_id.name;
+        for position in self.index.values_mut() {
+            if *position > index {
+                *position -= 1;
             }
         }
-        // Sell discontinuous stocks, create a list of what to sell
-        let mut indicies_to_bin: Vec<(usize, StockID)> = Vec::new();
-        for (i, (_, stock)) in player.stocks_purchased.iter().enumerate() {  // THIS ITER IS ORDERED BY DEFINITION
-            if stock.stock_id.time_id.is_date(&self.time_id) {
-                let mut indicies_to_save: Vec<usize> = Vec::new();
-                for (j, (_, bin_stock_id)) in indicies_to_bin.iter().enumerate() {
-                    if stock.stock_id.is_name(&bin_stock_id) {
-                        if bin_stock_id.is_immediate_previous_of(&stock.stock_id) {
-                            indicies_to_save.push(j);
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-                }
-                // Now remove those in the save list from the bin list
-                for j in indicies_to_save.iter().rev() {
-                    indicies_to_bin.remove(*j);
-                }
-            } else {
-                indicies_to_bin.push((i, stock.stock_id.clone()));  // throw away everything not in this quarter
-            }
-        }
-        // Fully constructed bin list, construct payoff and chuck
-        for i in indicies_to_bin.iter().rev().map(|(i, _stock)| i) {
-            let (buy_price, stock) = &player.stocks_purchased[*i];
-            let sell_price;
-            match float_quarter.find_by_stock_name(stock) {
-                Some(current_value) => {
-                    sell_price = current_value.get(index).unwrap();
-                    player.spend += buy_price;
-                    player.spend_return += sell_price;
-                },
-                None => {   // stock no longer exists, you bought and couldn't sell
-                    sell_price = *buy_price;
-                    player.spend += buy_price;
-                }
-            }
-            let (buy_price, stock_removed) = player.stocks_purchased.remove(*i);
-            player.stocks_sold.push((buy_price, sell_price, stock_removed));
+        if self.index.get(&name) == Some(&index) {
+            // A later record of the same name becomes the first appearance
+            match self.quarter_vector[index..].iter().position(|stock| stock.stock_id.name == name) {
+                Some(offset) => self.index.insert(name, index + offset),
+                None => self.index.remove(&name)
+            };
         }
+        removed
+    }
+    /// Keeps only the DataRecords for which the predicate is true.
+    ///
+    /// # Arguments
+    /// * `keep` - The predicate deciding which DataRecords to keep.
+    pub fn retain<F: FnMut(&DataRecord<T>) -> bool>(&mut self, keep: F) {
+        self.quarter_vector.retain(keep);
+        self.rebuild_index();
+    }
+    /// Returns true if a stock with the given name is in the Quarter.
+    ///
+    /// # Arguments
+    /// * `name` - The stock name to look for.
+    pub fn contains_ticker(&self, name: &Ticker) -> bool {
+        self.index.contains_key(name)
+    }
+    /// Finds the DataRecord (if it exists) with the given stock name.
+    ///
+    /// # Arguments
+    /// * `name` - The stock name to look for.
+    pub fn find_by_ticker(&self, name: &Ticker) -> Option<&DataRecord<T>> {
+        self.index.get(name).map(|i| &self.quarter_vector[*i])
     }
     /// Finds a DataRecord (if it exists) that has the same ".stock_id.name" as the input DataRecord.
     ///
     /// # Arguments
     /// * `entry` - A DataRecord to find in the Quarter.
-    pub fn find_by_stock_name<'a, U: DataTrait>(&'a self, entry: &DataRecord<U>) -> Option<&'a DataRecord<T>> {
-        for stock in &self.quarter_vector {
-            if stock.is_name(entry) {
-                return Some(&stock)
+    pub fn find_by_stock_name<U: DataTrait>(&self, entry: &DataRecord<U>) -> Option<&DataRecord<T>> {
+        self.find_by_ticker(&entry.stock_id.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::StockID;
+
+    fn quarter(names: &[&str]) -> Quarter<f64> {
+        let time_id = TimeID {
+            year: 2010,
+            period: 1,
+            frequency: Frequency::Quarterly
+        };
+        Quarter::new(names.iter().enumerate().map(|(i, name)| DataRecord {
+            record: vec![Some(i as f64)],
+            stock_id: StockID {
+                name: Ticker::intern(name),
+                time_id: time_id,
+                iteration: 0
             }
+        }).collect(), time_id)
+    }
+
+    fn position(quarter: &Quarter<f64>, name: &str) -> Option<f64> {
+        quarter.find_by_ticker(&Ticker::intern(name)).and_then(|stock| stock.get(0))
+    }
+
+    #[test]
+    fn remove_keeps_the_index_of_later_records() {
+        let mut quarter = quarter(&["QA", "QB", "QC", "QD"]);
+        assert_eq!(quarter.remove(1).get(0), Some(1.0));
+        assert_eq!(position(&quarter, "QA"), Some(0.0));
+        assert_eq!(position(&quarter, "QB"), None);
+        assert_eq!(position(&quarter, "QC"), Some(2.0));
+        assert_eq!(position(&quarter, "QD"), Some(3.0));
+    }
+
+    #[test]
+    fn records_mut_keeps_the_index() {
+        let mut quarter = quarter(&["QH", "QI"]);
+        for stock in quarter.records_mut() {
+            stock.record[0] = stock.record[0].map(|value| value + 10.0);
+            *stock.iteration = 1;
         }
-        println!("ERROR: Stock no longer exists - {:?}", entry.stock_id.name);
-        return None
+        assert_eq!(position(&quarter, "QI"), Some(11.0));
+        assert!(quarter.iter().all(|stock| stock.stock_id.iteration == 1));
+    }
+
+    #[test]
+    fn remove_indexes_a_later_duplicate() {
+        let mut quarter = quarter(&["QE", "QF", "QG", "QF"]);
+        quarter.remove(1);
+        assert_eq!(position(&quarter, "QF"), Some(3.0));
+        quarter.remove(2);
+        assert_eq!(position(&quarter, "QF"), None);
+        assert_eq!(position(&quarter, "QG"), Some(2.0));
     }
 }
@@ -1,10 +1,16 @@
-use std::{fmt, env::*, slice::Iter};
+use std::{fmt, collections::{BTreeMap, HashMap}, path::{Path, PathBuf}, slice::Iter};
 use csv::Reader;
 use rand::Rng;
 
+use crate::alignment::AlignmentReport;
 use crate::data_trait::DataTrait;
 use crate::quarter::Quarter;
-use crate::data_record::{TimeID, StockID, DataRecord};
+use crate::data_record::{Frequency, TimeID, StockID, DataRecord};
+use crate::error::Error;
+use crate::loading::LoadSettings;
+use crate::normalisation::Normalisation;
+use crate::percentile::{PercentileSettings, PercentileTable};
+use crate::ticker::Ticker;
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -22,149 +28,207 @@ impl<T: DataTrait> fmt::Display for Quarters<T> {
 }
 
 impl<T: DataTrait> Quarters<T> {
-    /// Generate the Quarters object from the default data directory (from this files location, the
-    /// folder is ../../test-data/TrimmedUnitedData).
-    pub fn new_quarters_from_default_file(iteration_max: usize) -> Quarters<f64> {
-        let mut pre_output: Vec<Quarter<f64>> = Vec::new();
-        // Populate with every blank quarter since epoch
-        let (mut year_count, mut quarter_count) = (1970, 1);
-        while year_count < 2020 {
-            pre_output.push(Quarter::load_blank(year_count, quarter_count));
-            if quarter_count == 4 {
-                year_count += 1;
-                quarter_count = 1;
-            } else {
-                quarter_count += 1;
+    /// Generate the Quarters object from a directory of trimmed and united CSV files.
+    ///
+    /// # Arguments
+    /// * `data_directory` - The directory, usually test-data/TrimmedUnitedData.
+    /// * `settings` - The frequency of the data, and the periods to load.
+    /// * `iteration_max` - The number of iterations, which stocks are randomly split between.
+    ///
+    /// # Remarks
+    /// Despite the name, each Quarter holds one period of the frequency of the settings, so it
+    /// may be a month or a year. A Quarter is made for each period that has data within the start
+    /// and end of the settings. Without an end, the Quarters end at the period with the most
+    /// stocks. Only stocks that are in the final period are kept. Files are read in name order,
+    /// so the records of each Quarter are in the same order every time the directory is loaded.
+    ///
+    /// Each record is moved onto the calendar period it covers, by the period end field or the
+    /// fiscal year end of its company in the alignment settings, before the start and end are
+    /// applied. The records that were moved are printed.
+    ///
+    /// Returns an error if the directory or a file can't be read, if a row of a file is malformed,
+    /// like one with too many fields, if a file is missing the year column, or the period column
+    /// when the data isn't annual, or the period end field when there is one, or has different
+    /// columns to the others, if a year, period or period end can't be parsed, if the start is
+    /// after the end, or if there is no data in the range.
+    pub fn new_quarters_from_directory(data_directory: &Path, settings: &LoadSettings, iteration_max: usize) -> Result<Quarters<f64>, Error> {
+        // The records of every period with data, by the index of its TimeID
+        let mut pre_output: BTreeMap<i64, Vec<DataRecord<f64>>> = BTreeMap::new();
+        let mut outside_range = 0;
+        let mut alignment_report = AlignmentReport::new_empty();
+        if let (Some(start), Some(end)) = (settings.start, settings.end) {
+            if !end.after(&start) {
+                return Err(Error::Config(format!("the start {} is after the end {}.", start.to_string(), end.to_string())));
             }
         }
         // Path to trimmed folder
-        let mut trim_unite_folder = current_dir().unwrap();
-        trim_unite_folder.pop(); trim_unite_folder.push("test-data/TrimmedUnitedData");
-        // Files list
-        let files_iter = trim_unite_folder.read_dir().unwrap().map(|r| r.unwrap()); // NOT SORTED
-        // Populate vector of readers
-        let files_and_names = files_iter.map(|file| {
-            let name = file.file_name().into_string().unwrap().split('_').next().unwrap().to_string();
-            (file, name)
-        });
+        let trim_unite_folder = data_directory.to_path_buf();
+        // Files list, sorted so that every load puts the stocks of a quarter in the same order
+        let mut paths = trim_unite_folder.read_dir().map_err(|why| Error::Io(trim_unite_folder.clone(), why))?.map(|file| file.map(|file| file.path())).collect::<Result<Vec<PathBuf>, _>>().map_err(|why| Error::Io(trim_unite_folder.clone(), why))?;
+        paths.sort();
         // Go through every file and assemble quarters
         let mut year_index = 0;
-        let mut quarter_index = 0;
-        let mut columns_found = false;
+        let mut quarter_index = None;
+        let mut period_end_index = None;
+        let mut headers = None;
         let mut field_names = Vec::new();
         let mut rng = rand::thread_rng();
-        for (file, name) in files_and_names {
-            let mut reader = Reader::from_path(file.path()).unwrap();
-            // Find the year and quarter columns (only done once, all files share this column index)
-            if !columns_found {
-                for (i, field) in reader.headers().unwrap().iter().enumerate() {
-                    if field == "year" {
-                        year_index = i;
-                    } else if field == "period" {
-                        quarter_index = i;
+        for path in paths {
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.split('_').next().unwrap().to_string(),
+                None => return Err(Error::Schema(format!("the file name of {} isn't valid UTF-8.", path.display())))
+            };
+            let ticker = Ticker::intern(&name);
+            let mut reader = Reader::from_path(&path).map_err(|why| Error::Csv(path.clone(), why))?;
+            let file_headers = reader.headers().map_err(|why| Error::Csv(path.clone(), why))?.clone();
+            // Find the year and quarter columns (only done once, all files must share the same columns)
+            match &headers {
+                None => {
+                    year_index = file_headers.iter().position(|field| field == "year").ok_or_else(|| Error::Schema(format!("{} has no \"year\" column.", path.display())))?;
+                    quarter_index = file_headers.iter().position(|field| field == "period");
+                    if quarter_index.is_none() && (settings.frequency != Frequency::Annual) {
+                        return Err(Error::Schema(format!("{} has no \"period\" column.", path.display())));
                     }
-                }
-                field_names = reader.headers().unwrap().iter().filter_map(|field| {
-                    if (field != "year") && (field != "period") {
-                        Some(field.to_string())
-                    } else {
-                        None
+                    if let Some(period_end_field) = &settings.alignment.period_end_field {
+                        period_end_index = Some(file_headers.iter().position(|field| field == period_end_field).ok_or_else(|| Error::Schema(format!("{} has no {:?} column.", path.display(), period_end_field)))?);
                     }
-                }).collect();
-                columns_found = true;
+                    field_names = file_headers.iter().enumerate().filter_map(|(i, field)| {
+                        if (field != "year") && (field != "period") && (Some(i) != period_end_index) {
+                            Some(field.to_string())
+                        } else {
+                            None
+                        }
+                    }).collect();
+                    headers = Some(file_headers);
+                },
+                Some(first_headers) => if *first_headers != file_headers {
+                    return Err(Error::Schema(format!("{} has different columns to the other files.", path.display())));
+                }
             }
             // Generate which iteration this should be used on
             let iteration = rng.gen_range(0, iteration_max);
             for row_wrapped in reader.records() {
-                if let Ok(row) = row_wrapped {
-                    // Get the row year and quarter as numbers
-                    let year = row.get(year_index).unwrap().parse::<i64>().unwrap();
-                    let quarter = row.get(quarter_index).unwrap()[1..=1].parse::<i64>().unwrap();
-                    // Create the DataRecord representation of the Record
-                    let mut data_record = DataRecord {
-                        record: Vec::new(),
-                        stock_id: StockID {
-                            name: name.clone(),
-                            time_id: TimeID {
-                                year: year,
-                                quarter: quarter
-                            },
-                            iteration: iteration
-                        }
-                    };
-                    for (i, field) in row.iter().enumerate() {
-                        if !((i == year_index) | (i == quarter_index)) {
-                            let parsed_field = field.parse::<f64>();
-                            match parsed_field {
-                                Ok(float_field) => data_record.push(Some(float_field)),
-                                Err(_err) => data_record.push(None), // if the field is empty
-                            }
+                let row = row_wrapped.map_err(|why| Error::Csv(path.clone(), why))?;
+                // Get the row year and period as a TimeID
+                let fiscal_time_id = Quarters::<f64>::parse_time(row.get(year_index), quarter_index.and_then(|index| row.get(index)), settings.frequency).map_err(|why| Error::Parse(format!("{} in {}", why, path.display())))?;
+                // Move it onto the calendar period it covers
+                let (time_id, aligned_by) = settings.alignment.align(&name, fiscal_time_id, period_end_index.and_then(|index| row.get(index))).map_err(|why| Error::Parse(format!("{} in {}", why, path.display())))?;
+                alignment_report.record(&name, &fiscal_time_id, &time_id, aligned_by);
+                if !settings.contains(&time_id) {
+                    outside_range += 1;
+                    continue;
+                }
+                // Create the DataRecord representation of the Record
+                let mut data_record = DataRecord {
+                    record: Vec::new(),
+                    stock_id: StockID {
+                        name: ticker,
+                        time_id: time_id,
+                        iteration: iteration
+                    }
+                };
+                for (i, field) in row.iter().enumerate() {
+                    if !((i == year_index) | (Some(i) == quarter_index) | (Some(i) == period_end_index)) {
+                        let parsed_field = field.parse::<f64>();
+                        match parsed_field {
+                            Ok(float_field) => data_record.push(Some(float_field)),
+                            Err(_err) => data_record.push(None), // if the field is empty
                         }
                     }
-                    // Put it into the quarter it belongs to
-                    pre_output.get_mut(((year - 1970) * 4 + (quarter - 1)) as usize)
-                              .unwrap()
-                              .push(data_record);
                 }
+                // Put it into the quarter it belongs to
+                pre_output.entry(time_id.index()).or_default().push(data_record);
             }
         }
-        // Issue from above: Files may still start and end at different times.
-        // Solution: Assemble quarters even if they don't hold enough. Then ditch them by using length after the fact.
-        println!("Finding largest quarter...");
-        let mut largest_time_id = TimeID {
-            year: 1970,
-            quarter: 1
-        };
-        let _largest_length = pre_output.iter().fold(0, |acc, quarter| {
-            let len = quarter.len();
-            if len >= acc {
-                println!("New largest quarter {} with value {}", quarter.time_id.to_string(), len);
-                largest_time_id = quarter.time_id.clone();
-                len
-            } else {
-                acc
-            }
-        });
-        let mut output: Vec<Quarter<f64>> = pre_output.into_iter().filter(|quarter| {
-            let keep = largest_time_id.after(&quarter.time_id) & (quarter.quarter_vector.len() > 0);
-            if !keep {
-                println!("Throwing away {}.", quarter.time_id.to_string());
-            }
-            keep
-        }).collect();
-        // Now ditch all stocks that don't exist in the final quarter
-        let final_quarter = &output[output.len() - 1];
-        let mut indicies_to_bin = vec![Vec::new(); output.len()];
-        for (i, quarter) in output.iter().enumerate() {
-            'a: for (j, stock) in quarter.iter().enumerate() {
-                for final_stock in final_quarter.iter() {
-                    if stock.is_name(&final_stock) {
-                        continue 'a;
-                    }
-                }
-                // If you are here, the stock wasn't found.
-                indicies_to_bin[i].push(j);
+        if !settings.alignment.is_default() {
+            for line in alignment_report.lines() {
+                println!("{}", line);
             }
+            println!("{}", alignment_report);
         }
-        for (i, quarter) in output.iter_mut().enumerate() {
-            for j in indicies_to_bin[i].iter().rev() {
-                quarter.remove(*j);
-            }
+        if outside_range > 0 {
+            println!("Skipped {} records outside of {}.", outside_range, settings);
         }
-        let starting_time = output[0].time_id.clone();
-        Quarters {
+        let ending_index = match Quarters::<f64>::ending_index(&pre_output, settings.end.is_some()) {
+            Some(index) => index,
+            None => return Err(Error::Schema(format!("there is no data in {} within {}.", trim_unite_folder.display(), settings)))
+        };
+        let thrown_away = pre_output.split_off(&(ending_index + 1));
+        if !thrown_away.is_empty() {
+            println!("Ending at {}, the period with the most stocks. Throwing away {} later periods.", TimeID::from_index(ending_index, settings.frequency).to_string(), thrown_away.len());
+        }
+        let mut output: Vec<Quarter<f64>> = pre_output.into_iter().map(|(index, records)| Quarter::new(records, TimeID::from_index(index, settings.frequency))).collect();
+        // Now ditch all stocks that don't exist in the final quarter
+        let final_index = output.len() - 1;
+        let (earlier_quarters, final_quarter) = output.split_at_mut(final_index);
+        for quarter in earlier_quarters.iter_mut() {
+            quarter.retain(|stock| final_quarter[0].contains_ticker(&stock.stock_id.name));
+        }
+        let starting_time = output[0].time_id;
+        let ending_time = output[final_index].time_id;
+        Ok(Quarters {
             field_names: field_names,
             quarters_vector: output,
             starting_time: starting_time,
-            ending_time: largest_time_id
+            ending_time: ending_time
+        })
+    }
+    /// Returns the index of the last period to load, or None if there are no periods.
+    ///
+    /// # Arguments
+    /// * `periods` - The records of every period with data, by the index of its TimeID.
+    /// * `explicit_end` - Whether the periods were already cut off at an end.
+    ///
+    /// # Remarks
+    /// Files may still start and end at different times, so without an explicit end the period
+    /// with the most stocks is taken as the end, the latest of them if there's a tie, and any
+    /// after it are thrown away.
+    fn ending_index<R>(periods: &BTreeMap<i64, Vec<R>>, explicit_end: bool) -> Option<i64> {
+        if explicit_end {
+            return periods.keys().next_back().copied();
+        }
+        periods.iter().fold(None, |acc: Option<(i64, usize)>, (index, records)| match acc {
+            Some((_, largest)) if records.len() < largest => acc,
+            _ => Some((*index, records.len()))
+        }).map(|(index, _)| index)
+    }
+    /// Parses the year and period of a row, where a quarter is written like "Q3" and a month like
+    /// "M03". Annual data doesn't need a period.
+    ///
+    /// # Arguments
+    /// * `year` - The year field of the row, if it has one.
+    /// * `period` - The period field of the row, if it has one.
+    /// * `frequency` - The frequency of the data.
+    fn parse_time(year: Option<&str>, period: Option<&str>, frequency: Frequency) -> Result<TimeID, String> {
+        let year_string = year.ok_or("a row has no year")?;
+        let year = year_string.trim().parse::<i64>().map_err(|_| format!("the year {:?} isn't a number", year_string))?;
+        let period_string = match (period, frequency) {
+            (_, Frequency::Annual) => "",
+            (Some(period), _) => period,
+            (None, _) => return Err("a row has no period".to_string())
+        };
+        match frequency.parse_period(period_string) {
+            Some(period) => Ok(TimeID {
+                year: year,
+                period: period,
+                frequency: frequency
+            }),
+            None => Err(format!("the period {:?} isn't a period from 1 to {} of {}", period_string, frequency.periods_per_year(), frequency))
         }
     }
+    /// Returns a line for each period with the number of stocks in it, and a line with the
+    /// range of the periods.
+    pub fn coverage_table(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.iter().map(|quarter| format!("Coverage {}: {} stocks.", quarter.time_id.to_string(), quarter.len())).collect();
+        lines.push(format!("Coverage: {} periods from {} to {}, {:.2} years.", self.len(), self.starting_time.to_string(), self.ending_time.to_string(), self.years()));
+        lines
+    }
     /// Creates an ordered vector (over the quarters) of vectors (over the fields) of every result
     ///  of the training data.
     pub fn expensive_training_data_analysis(&self) -> Vec<Vec<Vec<T>>> {
         let mut quarter_accumulator: Vec<Vec<Vec<T>>> = vec![vec![Vec::new(); self.field_names.len()]; self.quarters_vector.len()];    // Vector (quarters) of vector (fields) of vector (results)
         for (current_quarter, quarter_store) in self.iter().zip(quarter_accumulator.iter_mut()) {
-            for ref row in &current_quarter.quarter_vector {
+            for row in current_quarter.iter() {
                 for (i, option_field) in row.iter().enumerate() {
                     match option_field {
                         Some(field) => quarter_store.get_mut(i).unwrap().push(*field),
@@ -180,104 +244,135 @@ impl<T: DataTrait> Quarters<T> {
         }
         quarter_accumulator
     }
-    ///
+    /// Creates a PercentileTable for every field of every quarter, or None where the field has
+    /// too few observations in that quarter.
     ///
     /// # Arguments
     /// * `denomination` - The distance between adjacent percentiles. This number must evenly
-    /// divide 100 with no remainder.
-    ///
-    /// # Remarks
-    /// The 0th element is the lower limit of the 1st percentile. The ith element is the lower
-    /// limit of the (i+1)th percentile.
-    fn create_percentile_vectors(&self, denomination: usize) -> Vec<Vec<Vec<T>>> {
-        // Compute percentile start values
-        let training_data = self.expensive_training_data_analysis();
-        let mut percentile_boundary_vectors = vec![vec![Vec::new(); self.field_names.len()]; training_data.len()];
-        for (i, quarter_vector) in percentile_boundary_vectors.iter_mut().enumerate() {
-            for (j, percentile_vector) in quarter_vector.iter_mut().enumerate() {
-                let ijth_training_data = &training_data[i][j];
-                let gap = (ijth_training_data.len() as f64) / ((100 / denomination) as f64);
-                for k in 1..(100 / denomination) {
-                    if ijth_training_data.len() == 0 {
-                        percentile_vector.push(T::zero());
-                    } else {
-                        percentile_vector.push(ijth_training_data[(gap * (k as f64)) as usize]);
-                    }
-                }
-            }
-        }
-        percentile_boundary_vectors
+    ///   divide 100 with no remainder, or an error is returned.
+    /// * `settings` - The percentile method and minimum sample size to use.
+    fn create_percentile_tables(&self, denomination: usize, settings: &PercentileSettings) -> Result<Vec<Vec<Option<PercentileTable>>>, Error> {
+        self.expensive_training_data_analysis().into_iter().map(|quarter_store| {
+            quarter_store.into_iter().map(|field_store| {
+                let sorted = field_store.iter().filter_map(|field| field.to_f64()).collect::<Vec<f64>>();
+                PercentileTable::new(sorted, denomination, settings)
+            }).collect()
+        }).collect()
     }
-    ///
+    /// Creates a new Quarters set where every field is replaced by its percentile bucket within
+    /// its quarter.
     ///
     /// # Arguments
     /// * `denomination` - The distance between adjacent percentiles. This number must evenly
-    /// divide 100 with no remainder.
+    ///   divide 100 with no remainder, or an error is returned.
+    /// * `settings` - The percentile method and minimum sample size to use.
     ///
-    pub fn create_percentile_quarters(&self, denomination: usize) -> Quarters<usize> {
-        let percentile_boundary_vectors = self.create_percentile_vectors(denomination);
-        // let mut temp_vec = Vec::new();
-        // for i in 0..(100/denomination) {
-        //     temp_vec.push((i+1)*denomination);
-        // }
-        // let mut temp_vec2 = Vec::new();
-        // for i in 0..self.len() {
-        //     temp_vec2.push(percentile_boundary_vectors[i][44].iter().zip(temp_vec.iter()).filter_map(|(data, perc)| {
-        //         if *perc == 40 {
-        //             Some(data)
-        //         } else {
-        //             None
-        //         }
-        //     }).collect::<Vec<_>>()[0]);
-        // }
-        // println!("{:?}", temp_vec2);
+    /// # Remarks
+    /// A field with fewer than `settings.min_samples` observations in a quarter becomes None for
+    /// every stock in that quarter.
+    pub fn create_percentile_quarters(&self, denomination: usize, settings: &PercentileSettings) -> Result<Quarters<usize>, Error> {
+        let percentile_tables = self.create_percentile_tables(denomination, settings)?;
         // Create new Quarters set
         let mut new_quarters_vector = Vec::new();
-        for (i, quarter) in self.iter().enumerate() {
+        for (quarter, quarter_tables) in self.iter().zip(percentile_tables.iter()) {
             let mut new_quarter_vector = Vec::new();
-            for data_record in &quarter.quarter_vector {
-                let mut new_record_vector = Vec::new();
-                'a: for (j, option_field) in data_record.record.iter().enumerate() {
-                    let percentile_vector = &percentile_boundary_vectors[i][j];
-                    'b: for (k, element) in percentile_vector.iter().enumerate() {
-                        match option_field {
-                            Some(field) => {
-                                if field > element {
-                                    continue 'b;
-                                } else {
-                                    new_record_vector.push(Some((k + 1) * denomination));
-                                    continue 'a;
-                                }
-                            },
-                            None => {
-                                new_record_vector.push(None);
-                                continue 'a;
-                            }
-                        }
-
+            for data_record in quarter.iter() {
+                let new_record_vector = data_record.iter().zip(quarter_tables.iter()).map(|(option_field, option_table)| {
+                    match (option_field, option_table) {
+                        (Some(field), Some(table)) => field.to_f64().map(|float_field| table.bucket(float_field)),
+                        _ => None
                     }
-                    // If you got here, field was always > element. => field is in the 100th
-                    // percentile. This was omitted from percentile_boundary_vectors.
-                    new_record_vector.push(Some(100));
-                }
+                }).collect();
                 new_quarter_vector.push(DataRecord {
                     record: new_record_vector,
-                    stock_id: data_record.stock_id.clone()
+                    stock_id: data_record.stock_id
                 });
             }
-            new_quarters_vector.push(Quarter {
-                quarter_vector: new_quarter_vector,
-                time_id: quarter.time_id.clone()
-            });
+            new_quarters_vector.push(Quarter::new(new_quarter_vector, quarter.time_id));
         }
-        Quarters {
+        Ok(Quarters {
+            field_names: self.field_names.clone(),
+            quarters_vector: new_quarters_vector,
+            starting_time: self.starting_time,
+            ending_time: self.ending_time
+        })
+    }
+    /// Creates a new Quarters set with every field transformed cross-sectionally (within each
+    /// quarter) into the space of the given Normalisation.
+    ///
+    /// # Arguments
+    /// * `normalisation` - The Normalisation to apply. Must not be Normalisation::Percentile,
+    ///   which is produced by create_percentile_quarters, or an error is returned.
+    pub fn create_normalised_quarters(&self, normalisation: &Normalisation) -> Result<Quarters<f64>, Error> {
+        let mut new_quarters_vector = Vec::new();
+        for quarter in self.iter() {
+            let mut new_records: Vec<Vec<Option<f64>>> = vec![Vec::new(); quarter.len()];
+            for j in 0..self.field_names.len() {
+                let column = quarter.iter().map(|data_record| data_record.get(j).and_then(|field| field.to_f64())).collect::<Vec<_>>();
+                for (new_record, new_field) in new_records.iter_mut().zip(normalisation.transform(&column)?) {
+                    new_record.push(new_field);
+                }
+            }
+            new_quarters_vector.push(Quarter::new(quarter.iter().zip(new_records).map(|(data_record, new_record)| DataRecord {
+                record: new_record,
+                stock_id: data_record.stock_id
+            }).collect(), quarter.time_id));
+        }
+        Ok(Quarters {
             field_names: self.field_names.clone(),
             quarters_vector: new_quarters_vector,
-            starting_time: self.starting_time.clone(),
-            ending_time: self.ending_time.clone()
+            starting_time: self.starting_time,
+            ending_time: self.ending_time
+        })
+    }
+    /// Randomly reassigns every stock to one of the iterations, keeping all of a stock's records
+    /// in the same iteration. Returns the assignment made.
+    ///
+    /// # Arguments
+    /// * `iteration_max` - The number of iterations.
+    pub fn assign_iterations(&mut self, iteration_max: usize) -> HashMap<Ticker, usize> {
+        let mut rng = rand::thread_rng();
+        let mut assignment = HashMap::new();
+        for quarter in self.iter() {
+            for stock in quarter.iter() {
+                assignment.entry(stock.stock_id.name).or_insert_with(|| rng.gen_range(0, iteration_max));
+            }
         }
+        self.set_iterations(&assignment);
+        assignment
     }
+    /// Sets the iteration of every stock from an assignment made by assign_iterations.
     ///
+    /// # Arguments
+    /// * `assignment` - The iteration of each stock.
+    pub fn set_iterations(&mut self, assignment: &HashMap<Ticker, usize>) {
+        for quarter in self.quarters_vector.iter_mut() {
+            for stock in quarter.records_mut() {
+                if let Some(iteration) = assignment.get(&stock.name) {
+                    *stock.iteration = *iteration;
+                }
+            }
+        }
+    }
+    /// Returns true if any record has a value of a field.
+    ///
+    /// # Arguments
+    /// * `field` - The index of the field.
+    pub fn is_observed(&self, field: usize) -> bool {
+        self.iter().any(|quarter| quarter.iter().any(|stock| stock.get(field).is_some()))
+    }
+    /// Returns true if every Quarter holds records of the same stocks, in the same order, at the
+    /// same time as the Quarters given, so that records can be matched between them by position.
+    ///
+    /// # Arguments
+    /// * `other` - The Quarters to compare with, usually the raw Quarters these were made from.
+    pub fn has_same_stocks<U: DataTrait>(&self, other: &Quarters<U>) -> bool {
+        (self.len() == other.len()) && self.iter().zip(other.iter()).all(|(quarter, other_quarter)| {
+            (quarter.time_id.index() == other_quarter.time_id.index()) && (quarter.len() == other_quarter.len()) && quarter.iter().zip(other_quarter.iter()).all(|(stock, other_stock)| stock.stock_id.name == other_stock.stock_id.name)
+        })
+    }
+    /// Returns the number of years from the start of the first period to the start of the last,
+    /// whatever the frequency of the periods.
     pub fn years(&self) -> f64 {
         self.starting_time.years_until(&self.ending_time)
     }
@@ -298,3 +393,42 @@ impl<T: DataTrait> Quarters<T> {
         self.quarters_vector.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quarters_between(starting_time: &str, ending_time: &str) -> Quarters<f64> {
+        Quarters {
+            field_names: Vec::new(),
+            quarters_vector: Vec::new(),
+            starting_time: TimeID::from_name(starting_time).unwrap(),
+            ending_time: TimeID::from_name(ending_time).unwrap()
+        }
+    }
+
+    #[test]
+    fn the_period_with_the_most_stocks_ends_the_data() {
+        let periods = [(8040, 3), (8041, 5), (8042, 5), (8043, 2)].iter().map(|(index, stocks)| (*index, vec![(); *stocks])).collect::<BTreeMap<i64, Vec<()>>>();
+        assert_eq!(Quarters::<f64>::ending_index(&periods, false), Some(8042));
+        assert_eq!(Quarters::<f64>::ending_index(&periods, true), Some(8043));
+        assert_eq!(Quarters::<f64>::ending_index(&BTreeMap::<i64, Vec<()>>::new(), false), None);
+    }
+
+    #[test]
+    fn a_malformed_row_is_an_error() {
+        let directory = std::env::temp_dir().join(format!("game-quarters-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("QA_data.csv"), "year,period,close\n2010,Q1,10\n2010,Q2,11,12\n").unwrap();
+        let loaded = Quarters::<f64>::new_quarters_from_directory(&directory, &LoadSettings::new_default(), 1);
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert!(matches!(loaded, Err(Error::Csv(_, _))));
+    }
+
+    #[test]
+    fn years_are_annualised_by_months() {
+        assert_eq!(quarters_between("2010-Q1", "2014-Q1").years(), 4.0);
+        assert_eq!(quarters_between("2010-M01", "2011-M07").years(), 1.5);
+        assert_eq!(quarters_between("2010", "2018").years(), 8.0);
+    }
+}
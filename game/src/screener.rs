@@ -1,6 +1,7 @@
 use std::{fmt, slice::Iter};
 use rand::Rng;
 
+use crate::columnar::{Bitmap, ColumnarQuarter};
 use crate::data_trait::DataTrait;
 use crate::quarters::Quarters;
 
@@ -11,6 +12,13 @@ pub enum Rule {
     Gt
 }
 
+/// The used portion of a Screener: the index, threshold bits and rule (true for Lt) of each used
+/// field. Two Screeners with the same key select exactly the same stocks.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq, Eq, Hash)]
+pub struct ScreenerKey(Vec<(usize, u64, bool)>);
+
 #[derive(Debug)]
 #[derive(Clone)]
 pub struct Screener<T: DataTrait> {
@@ -29,27 +37,34 @@ impl<T: DataTrait> Screener<T> {
     /// # Arguments
     /// * `l_limits` - The lower limits for each element of the Screener.
     /// * `r_limits` - The upper limits for each element of the Screener.
+    /// * `banned_fields` - The fields that are never used.
+    /// * `required_fields` - The fields that are always used.
     ///
     /// # Remarks
-    /// Each argument is a vector that is as long as the Screener that needs to be generated.
+    /// Each limit is a vector that is as long as the Screener that needs to be generated.
     /// The ith element of the Screener is greater than the ith element of l_limits, and less than
     /// the ith element of r_limits.
-    pub fn new_uniform_random((l_limits, u_limits): (&Vec<T>, &Vec<T>), banned_fields: &Vec<usize>, percentile_gap: usize) -> Screener<T> {
+    pub fn new_uniform_random((l_limits, u_limits): (&Vec<T>, &Vec<T>), banned_fields: &[usize], required_fields: &[usize], percentile_gap: usize) -> Screener<T> {
         let mut output = Vec::new();
         let mut rng = rand::thread_rng();
         for (i, (l, u)) in l_limits.iter().zip(u_limits).enumerate() {
             let field_used = !banned_fields.contains(&i);
-            if l == u {
-                output.push((*l, rng.gen_bool(10.0 / 130.0) & field_used, if rng.gen_bool(0.5) {Rule::Lt} else {Rule::Gt}));
+            let field_required = required_fields.contains(&i);
+            if l > u {
+                // The field was never observed, so it can never be used.
+                output.push((T::zero(), false, Rule::Gt));
+            } else if l == u {
+                output.push((*l, (rng.gen_bool(10.0 / 130.0) & field_used) | field_required, if rng.gen_bool(0.5) {Rule::Lt} else {Rule::Gt}));
             } else {
-                output.push((rng.gen_range(*l, *u).round(percentile_gap), rng.gen_bool(10.0 / 130.0) & field_used, if rng.gen_bool(0.5) {Rule::Lt} else {Rule::Gt}));
+                output.push((rng.gen_range(*l, *u).round(percentile_gap), (rng.gen_bool(10.0 / 130.0) & field_used) | field_required, if rng.gen_bool(0.5) {Rule::Lt} else {Rule::Gt}));
             }
         }
         Screener {
             screen: output
         }
     }
-    /// Perform a uniform crossover of two Screeners.
+    /// Perform an averaging crossover of two Screeners. Each threshold is the mean of the parents'
+    /// thresholds, and the used flag and rule are taken from either with equal probability.
     ///
     /// # Arguments
     /// * `slice` - The Screener to crossover with.
@@ -70,6 +85,76 @@ impl<T: DataTrait> Screener<T> {
                         .collect()
         }
     }
+    /// Perform a one-point crossover of two Screeners. Every field before a random cut point is
+    /// taken whole from this Screener, and every field after it from the other.
+    ///
+    /// # Arguments
+    /// * `slice` - The Screener to crossover with.
+    pub fn one_point_crossover(&self, slice: &Screener<T>) -> Screener<T> {
+        let cut = rand::thread_rng().gen_range(0, self.len() + 1);
+        self.splice(slice, cut, self.len())
+    }
+    /// Perform a two-point crossover of two Screeners. Every field between two random cut points
+    /// is taken whole from the other Screener, and every other field from this one.
+    ///
+    /// # Arguments
+    /// * `slice` - The Screener to crossover with.
+    pub fn two_point_crossover(&self, slice: &Screener<T>) -> Screener<T> {
+        let mut rng = rand::thread_rng();
+        let (first_cut, second_cut) = (rng.gen_range(0, self.len() + 1), rng.gen_range(0, self.len() + 1));
+        self.splice(slice, first_cut.min(second_cut), first_cut.max(second_cut))
+    }
+    /// Takes the fields in [start, end) from the other Screener, and every other field from this
+    /// one.
+    fn splice(&self, slice: &Screener<T>, start: usize, end: usize) -> Screener<T> {
+        Screener {
+            screen: self.iter()
+                        .zip(slice.iter())
+                        .enumerate()
+                        .map(|(i, (l, r))| if (start <= i) & (i < end) {r.clone()} else {l.clone()})
+                        .collect()
+        }
+    }
+    /// Perform a uniform crossover of two Screeners, where each field is taken whole from either
+    /// Screener with equal probability.
+    ///
+    /// # Arguments
+    /// * `slice` - The Screener to crossover with.
+    pub fn uniform_crossover(&self, slice: &Screener<T>) -> Screener<T> {
+        let mut rng = rand::thread_rng();
+        Screener {
+            screen: self.iter()
+                        .zip(slice.iter())
+                        .map(|(l, r)| if rng.gen_bool(0.5) {l.clone()} else {r.clone()})
+                        .collect()
+        }
+    }
+    /// Perform a crossover of two Screeners as sets of used rules. A rule used by both Screeners
+    /// is always kept, taken from either with equal probability, and a rule used by only one is
+    /// kept with probability 0.5.
+    ///
+    /// # Arguments
+    /// * `slice` - The Screener to crossover with.
+    ///
+    /// # Remarks
+    /// A field required by both Screeners is always used by the result, and a field neither
+    /// uses never is.
+    pub fn rule_set_crossover(&self, slice: &Screener<T>) -> Screener<T> {
+        let mut rng = rand::thread_rng();
+        Screener {
+            screen: self.iter()
+                        .zip(slice.iter())
+                        .map(|(l, r)| {
+                            match (l.1, r.1) {
+                                (true, true) => if rng.gen_bool(0.5) {l.clone()} else {r.clone()},
+                                (true, false) => if rng.gen_bool(0.5) {l.clone()} else {(l.0, false, l.2.clone())},
+                                (false, true) => if rng.gen_bool(0.5) {r.clone()} else {(r.0, false, r.2.clone())},
+                                (false, false) => l.clone()
+                            }
+                        })
+                        .collect()
+        }
+    }
     /// Perform a lazy mutation on the Screener. This mutation is a per element multiplier
     /// uniformly selected from the interval [0.9, 1.1].
     ///
@@ -96,6 +181,29 @@ impl<T: DataTrait> Screener<T> {
                         .collect()
         }
     }
+    /// Clears from a selection every stock of a ColumnarQuarter that fails a used rule of the
+    /// Screener. Stocks without a value for a field pass that field's rule.
+    ///
+    /// # Arguments
+    /// * `quarter` - The ColumnarQuarter being screened.
+    /// * `selection` - One bit per stock of the quarter, cleared where the stock fails.
+    pub fn select(&self, quarter: &ColumnarQuarter<T>, selection: &mut Bitmap) {
+        for ((threshold, used, rule), column) in self.iter().zip(quarter.columns.iter()) {
+            if *used {
+                let threshold = *threshold;
+                match rule {
+                    Rule::Lt => selection.filter(&column.values, &column.validity, |value| value <= threshold),
+                    Rule::Gt => selection.filter(&column.values, &column.validity, |value| value >= threshold)
+                }
+            }
+        }
+    }
+    /// Returns the key of the used portion of the Screener.
+    pub fn key(&self) -> ScreenerKey {
+        ScreenerKey(self.iter().enumerate().filter(|(_, (_, used, _))| *used).map(|(i, (threshold, _, rule))| {
+            (i, threshold.to_f64().unwrap().to_bits(), match rule {Rule::Lt => true, Rule::Gt => false})
+        }).collect())
+    }
     /// Returns the length of the Screener
     pub fn len(&self) -> usize {
         self.screen.len()
@@ -114,13 +222,4 @@ impl<T: DataTrait> Screener<T> {
             }
         }).collect::<Vec<_>>()
     }
-    ///
-    pub fn is_similar_to(&self, screener: &Screener<T>, ratio: f64) -> bool {
-        let zip = self.iter().zip(screener.iter());
-        zip.clone().fold(0.0, |acc, ((_, l_used, _), (_, r_used, _))| {
-            acc + if l_used & r_used {1.0} else {0.0}
-        }) / zip.fold(0.0, |acc, ((_, l_used, _), (_, r_used, _))| {
-            acc + if l_used | r_used {1.0} else {0.0}
-        }) > ratio
-    }
 }
@@ -0,0 +1,214 @@
+use std::fmt;
+use rand::Rng;
+
+use crate::game::DEFAULT_TOURNEY_CONST;
+
+/// How parents are chosen from a population, by fitness.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum Selection {
+    /// The fittest of k players picked uniformly at random.
+    Tournament(usize),
+    /// Each parent is picked with probability proportional to its fitness.
+    Roulette,
+    /// Like Roulette, but every parent is picked in one spin with equally spaced pointers, so the
+    /// number of times a player is picked is always within one of its expected count.
+    StochasticUniversal,
+    /// Each parent is picked with a probability that grows linearly with its rank, from
+    /// (2 - s) / n for the least fit to s / n for the fittest, with pressure s between 1 and 2.
+    Rank(f64),
+    /// Parents are picked uniformly from the given fraction of the fittest players.
+    Truncation(f64)
+}
+
+/// The selection operator, and which players are kept unchanged in the next generation.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SelectionSettings {
+    pub method: Selection,
+    /// The number of the best players carried over to the next generation.
+    pub elites: usize,
+    /// An elite must have sold more than this many stocks.
+    pub elite_min_sales: usize
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selection::Tournament(k) => write!(f, "Tournament[k: {}]", k),
+            Selection::Roulette => write!(f, "Roulette"),
+            Selection::StochasticUniversal => write!(f, "StochasticUniversal"),
+            Selection::Rank(pressure) => write!(f, "Rank[pressure: {}]", pressure),
+            Selection::Truncation(fraction) => write!(f, "Truncation[fraction: {}]", fraction)
+        }
+    }
+}
+
+impl fmt::Display for SelectionSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SelectionSettings[method: {}, elites: {}, elite_min_sales: {}]", self.method, self.elites, self.elite_min_sales)
+    }
+}
+
+impl Selection {
+    /// Parse a Selection from its command line name, with its parameter.
+    ///
+    /// # Arguments
+    /// * `name` - One of "tournament", "roulette", "sus", "rank" or "truncation".
+    /// * `tournament_size` - The k used by "tournament".
+    /// * `rank_pressure` - The selection pressure used by "rank".
+    /// * `truncation` - The fraction of players kept by "truncation".
+    pub fn from_name(name: &str, tournament_size: usize, rank_pressure: f64, truncation: f64) -> Option<Selection> {
+        match name {
+            "tournament" => Some(Selection::Tournament(tournament_size)),
+            "roulette" => Some(Selection::Roulette),
+            "sus" => Some(Selection::StochasticUniversal),
+            "rank" => Some(Selection::Rank(rank_pressure)),
+            "truncation" => Some(Selection::Truncation(truncation)),
+            _ => None
+        }
+    }
+    /// Picks parents from a population. Returns the index of each parent.
+    ///
+    /// # Arguments
+    /// * `fitness` - The fitness of each player, which can't be negative.
+    /// * `count` - The number of parents to pick.
+    ///
+    /// # Remarks
+    /// If every player has a fitness of zero, the fitness proportional methods pick uniformly.
+    /// This will fail at runtime if called on an empty population, or a tournament with k = 0.
+    pub fn select(&self, fitness: &[f64], count: usize) -> Vec<usize> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let mut rng = rand::thread_rng();
+        match self {
+            Selection::Tournament(k) => {
+                if *k == 0 {
+                    panic!("Tournament Selection with k = 0 occurred. Unrecoverable error.");
+                }
+                (0..count).map(|_| {
+                    let mut candidate = rng.gen_range(0, fitness.len());
+                    for _i in 1..*k {
+                        let next_candidate = rng.gen_range(0, fitness.len());
+                        if fitness[next_candidate] > fitness[candidate] {
+                            candidate = next_candidate;
+                        }
+                    }
+                    candidate
+                }).collect()
+            },
+            Selection::Roulette => {
+                let weights = Selection::weights_or_uniform(fitness);
+                let total = weights.iter().sum::<f64>();
+                (0..count).map(|_| Selection::spin(&weights, rng.gen_range(0.0, total))).collect()
+            },
+            Selection::StochasticUniversal => {
+                let weights = Selection::weights_or_uniform(fitness);
+                let spacing = weights.iter().sum::<f64>() / (count as f64);
+                let start = rng.gen_range(0.0, spacing);
+                let mut picked = Vec::new();
+                let mut cumulative = 0.0;
+                let mut i = 0;
+                for pointer in (0..count).map(|j| start + (j as f64) * spacing) {
+                    while (i < weights.len() - 1) && (cumulative + weights[i] <= pointer) {
+                        cumulative += weights[i];
+                        i += 1;
+                    }
+                    picked.push(i);
+                }
+                // Shuffle so that consecutive parents aren't always neighbours in the population
+                rng.shuffle(&mut picked);
+                picked
+            },
+            Selection::Rank(pressure) => {
+                let n = fitness.len() as f64;
+                let mut weights = vec![0.0; fitness.len()];
+                for (rank, i) in Selection::order(fitness).into_iter().rev().enumerate() {
+                    weights[i] = if n > 1.0 {(2.0 - pressure) / n + 2.0 * (rank as f64) * (pressure - 1.0) / (n * (n - 1.0))} else {1.0};
+                }
+                let total = weights.iter().sum::<f64>();
+                (0..count).map(|_| Selection::spin(&weights, rng.gen_range(0.0, total))).collect()
+            },
+            Selection::Truncation(fraction) => {
+                let kept = ((fitness.len() as f64) * fraction).ceil().clamp(1.0, fitness.len() as f64) as usize;
+                let fittest = Selection::order(fitness);
+                (0..count).map(|_| fittest[rng.gen_range(0, kept)]).collect()
+            }
+        }
+    }
+    /// Returns the indicies of the players from fittest to least fit.
+    pub fn order(fitness: &[f64]) -> Vec<usize> {
+        let mut order = (0..fitness.len()).collect::<Vec<usize>>();
+        order.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+        order
+    }
+    fn weights_or_uniform(fitness: &[f64]) -> Vec<f64> {
+        if fitness.iter().any(|value| *value > 0.0) {
+            fitness.iter().map(|value| value.max(0.0)).collect()
+        } else {
+            vec![1.0; fitness.len()]
+        }
+    }
+    /// Returns the index whose slice of the cumulative weights contains a point.
+    fn spin(weights: &[f64], point: f64) -> usize {
+        let mut cumulative = 0.0;
+        for (i, weight) in weights.iter().enumerate() {
+            cumulative += weight;
+            if point < cumulative {
+                return i;
+            }
+        }
+        weights.len() - 1
+    }
+}
+
+impl SelectionSettings {
+    /// The default settings: tournaments of size 3, and no elites.
+    pub fn new_default() -> SelectionSettings {
+        SelectionSettings {
+            method: Selection::Tournament(DEFAULT_TOURNEY_CONST),
+            elites: 0,
+            elite_min_sales: 40
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(picked: &[usize], n: usize) -> Vec<usize> {
+        (0..n).map(|i| picked.iter().filter(|pick| **pick == i).count()).collect()
+    }
+
+    #[test]
+    fn tournaments_pick_the_fitter_player() {
+        let picked = Selection::Tournament(64).select(&[0.5, 2.0], 20);
+        assert_eq!(counts(&picked, 2), vec![0, 20]);
+        assert_eq!(Selection::Tournament(3).select(&[1.0], 2), vec![0, 0]);
+    }
+
+    #[test]
+    fn roulettes_never_pick_a_player_without_fitness() {
+        assert_eq!(counts(&Selection::Roulette.select(&[0.0, 5.0, 0.0], 30), 3), vec![0, 30, 0]);
+        assert_eq!(Selection::Roulette.select(&[0.0, 0.0], 50).len(), 50);
+    }
+
+    #[test]
+    fn stochastic_universal_sampling_picks_the_expected_counts() {
+        assert_eq!(counts(&Selection::StochasticUniversal.select(&[1.0, 1.0, 2.0], 4), 3), vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn full_rank_pressure_only_picks_the_fittest_of_two() {
+        assert_eq!(counts(&Selection::Rank(2.0).select(&[3.0, 1.0], 20), 2), vec![20, 0]);
+    }
+
+    #[test]
+    fn truncation_picks_from_the_fittest_fraction() {
+        let picked = Selection::Truncation(0.5).select(&[1.0, 4.0, 2.0, 3.0], 40);
+        assert!(picked.iter().all(|pick| (*pick == 1) || (*pick == 3)));
+        assert_eq!(Selection::order(&[1.0, 4.0, 2.0, 3.0]), vec![1, 3, 2, 0]);
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+
+use crate::crossover::Crossover;
+use crate::fields::FieldSettings;
+use crate::game::{DEFAULT_FITNESS_CACHE_SIZE, DEFAULT_HALL_OF_FAME_SIZE};
+use crate::islands::IslandSettings;
+use crate::loading::LoadSettings;
+use crate::mutation::MutationSettings;
+use crate::niching::NichingSettings;
+use crate::paths::PathSettings;
+use crate::normalisation::Normalisation;
+use crate::percentile::PercentileSettings;
+use crate::selection::SelectionSettings;
+use crate::stopping::StoppingSettings;
+use crate::universe::Universe;
+
+/// Every parameter of an experiment, as set on the command line.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Settings {
+    pub population_sizes: Vec<usize>,
+    pub generation_maxs: Vec<usize>,
+    pub iterations: Vec<usize>,
+    pub percentiles: Vec<usize>,
+    pub runs: usize,
+    pub loading: LoadSettings,
+    pub normalisation: Normalisation,
+    pub percentile_settings: PercentileSettings,
+    pub universe: Universe,
+    pub fields: FieldSettings,
+    pub paths: PathSettings,
+    /// The cache directory, relative to the root of the paths.
+    pub cache_directory: Option<String>,
+    pub threads: usize,
+    pub islands: IslandSettings,
+    pub niching: NichingSettings,
+    pub selection: SelectionSettings,
+    pub crossover: Crossover,
+    pub mutation: MutationSettings,
+    pub hall_of_fame: usize,
+    /// The number of Backtests the fitness cache keeps between generations.
+    pub fitness_cache: usize,
+    pub stopping: StoppingSettings
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Settings[runs: {}, lambda: {:?}, gen_max: {:?}, iter: {:?}, percentiles: {:?}, loading: {}, normalisation: {}, percentile_settings: {}, universe: {}, fields: {}, paths: {}, cache_directory: {:?}, threads: {}, islands: {}, niching: {}, selection: {}, crossover: {}, mutation: {}, hall_of_fame: {}, fitness_cache: {}, stopping: {}]", self.runs, self.population_sizes, self.generation_maxs, self.iterations, self.percentiles, self.loading, self.normalisation, self.percentile_settings, self.universe, self.fields, self.paths, self.cache_directory, self.threads, self.islands, self.niching, self.selection, self.crossover, self.mutation, self.hall_of_fame, self.fitness_cache, self.stopping)
+    }
+}
+
+impl Settings {
+    /// Creates the default Settings.
+    pub fn new_default() -> Settings {
+        Settings {
+            population_sizes: vec![100],
+            generation_maxs: vec![10],
+            iterations: vec![3],
+            percentiles: vec![10],
+            runs: 10,
+            loading: LoadSettings::new_default(),
+            normalisation: Normalisation::Percentile,
+            percentile_settings: PercentileSettings::new_default(),
+            universe: Universe::new_default(),
+            fields: FieldSettings::new_default(),
+            paths: PathSettings::new_default(),
+            cache_directory: Some("test-data/cache".to_string()),
+            threads: 0,
+            islands: IslandSettings::new_default(),
+            niching: NichingSettings::new_default(),
+            selection: SelectionSettings::new_default(),
+            crossover: Crossover::Averaging,
+            mutation: MutationSettings::new_default(),
+            hall_of_fame: DEFAULT_HALL_OF_FAME_SIZE,
+            fitness_cache: DEFAULT_FITNESS_CACHE_SIZE,
+            stopping: StoppingSettings::new_default()
+        }
+    }
+}
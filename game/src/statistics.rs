@@ -0,0 +1,215 @@
+use std::{fmt, fs::{self, File}, path::{Path, PathBuf}};
+
+use crate::data_trait::DataTrait;
+use crate::error::Error;
+use crate::niching::Diversity;
+use crate::observer::Observer;
+
+/// The state of the population of one generation, after it was run through the test data.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct GenerationStats {
+    pub iteration: usize,
+    pub generation: usize,
+    pub players_with_payoff: usize,
+    pub average_payoff: f64,
+    /// The highest percentage gain per year of a profitable player, if there was one.
+    pub best_payoff: Option<f64>,
+    /// The minimum, mean, median and maximum transformed payoff, used as fitness by selection.
+    pub fitness: FitnessSummary,
+    pub diversity: Diversity,
+    /// The number of stocks sold by every player together.
+    pub trades: usize,
+    pub unique_screeners: usize,
+    pub cache_hits: usize,
+    /// The seconds since the game started running.
+    pub elapsed: f64
+}
+
+/// The spread of the fitness of a population.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct FitnessSummary {
+    pub min: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub max: f64
+}
+
+/// A CSV file with a row of GenerationStats per generation, written as an Observer of a Game.
+/// After a failed write nothing more is written.
+#[derive(Debug)]
+pub struct StatsLog {
+    writer: Option<csv::Writer<File>>
+}
+
+impl fmt::Display for GenerationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let best = match self.best_payoff {
+            Some(payoff) => format!("{:.3}%", payoff),
+            None => "Didn't exist".to_string()
+        };
+        write!(f, "GenerationStats[iteration: {}, generation: {}, players_with_payoff: {}, average_payoff: {:.3}%, best_payoff: {}, fitness: {}, diversity: {}, trades: {}, unique_screeners: {}, cache_hits: {}, elapsed: {:.3}s]", self.iteration, self.generation, self.players_with_payoff, self.average_payoff, best, self.fitness, self.diversity, self.trades, self.unique_screeners, self.cache_hits, self.elapsed)
+    }
+}
+
+impl fmt::Display for FitnessSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FitnessSummary[min: {:.3}, mean: {:.3}, median: {:.3}, max: {:.3}]", self.min, self.mean, self.median, self.max)
+    }
+}
+
+impl GenerationStats {
+    /// The names of the columns of a StatsLog.
+    pub fn header() -> Vec<&'static str> {
+        vec!["iteration", "generation", "players_with_payoff", "average_payoff", "best_payoff", "fitness_min", "fitness_mean", "fitness_median", "fitness_max", "mean_distance", "niches", "trades", "unique_screeners", "cache_hits", "elapsed"]
+    }
+    /// The row of a StatsLog for this generation. A missing best payoff is left empty.
+    pub fn record(&self) -> Vec<String> {
+        vec![
+            self.iteration.to_string(),
+            self.generation.to_string(),
+            self.players_with_payoff.to_string(),
+            self.average_payoff.to_string(),
+            self.best_payoff.map(|payoff| payoff.to_string()).unwrap_or_default(),
+            self.fitness.min.to_string(),
+            self.fitness.mean.to_string(),
+            self.fitness.median.to_string(),
+            self.fitness.max.to_string(),
+            self.diversity.mean_distance.to_string(),
+            self.diversity.niches.to_string(),
+            self.trades.to_string(),
+            self.unique_screeners.to_string(),
+            self.cache_hits.to_string(),
+            self.elapsed.to_string()
+        ]
+    }
+}
+
+impl FitnessSummary {
+    /// Summarises the fitness of a population. An empty population has a summary of zeroes.
+    ///
+    /// # Arguments
+    /// * `fitness` - The fitness of each player.
+    pub fn from_fitness(fitness: &[f64]) -> FitnessSummary {
+        if fitness.is_empty() {
+            return FitnessSummary {
+                min: 0.0,
+                mean: 0.0,
+                median: 0.0,
+                max: 0.0
+            };
+        }
+        let mut sorted = fitness.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let middle = sorted.len() / 2;
+        FitnessSummary {
+            min: sorted[0],
+            mean: sorted.iter().sum::<f64>() / (sorted.len() as f64),
+            median: if sorted.len().is_multiple_of(2) {(sorted[middle - 1] + sorted[middle]) / 2.0} else {sorted[middle]},
+            max: sorted[sorted.len() - 1]
+        }
+    }
+}
+
+impl StatsLog {
+    /// Returns the path of the statistics file of an output file, alongside it and named after
+    /// it with "-stats.csv" in place of its extension.
+    ///
+    /// # Arguments
+    /// * `output` - The path the run is saved as.
+    pub fn path_for_output(output: &Path) -> PathBuf {
+        let mut path = output.to_path_buf();
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        path.set_file_name(format!("{}-stats.csv", stem));
+        path
+    }
+    /// Creates a StatsLog at a path, writing its header. The directory of the path is created if
+    /// it doesn't exist.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the CSV file, which is overwritten.
+    pub fn create(path: &Path) -> Result<StatsLog, Error> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|why| Error::Io(directory.to_path_buf(), why))?;
+        }
+        let mut writer = csv::Writer::from_path(path).map_err(|why| Error::Csv(path.to_path_buf(), why))?;
+        writer.write_record(GenerationStats::header()).map_err(|why| Error::Csv(path.to_path_buf(), why))?;
+        Ok(StatsLog {
+            writer: Some(writer)
+        })
+    }
+    /// Appends the row of a generation, flushing it so the log can be followed while the game
+    /// runs.
+    ///
+    /// # Arguments
+    /// * `stats` - The statistics of the generation.
+    pub fn write(&mut self, stats: &GenerationStats) -> Result<(), String> {
+        if let Some(writer) = &mut self.writer {
+            writer.write_record(stats.record()).map_err(|why| format!("couldn't write statistics: {}", why))?;
+            writer.flush().map_err(|why| format!("couldn't write statistics: {}", why))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: DataTrait> Observer<T> for StatsLog {
+    fn generation_evaluated(&mut self, stats: &GenerationStats) {
+        if let Err(why) = self.write(stats) {
+            println!("{}", why);
+            self.writer = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(generation: usize, best_payoff: Option<f64>) -> GenerationStats {
+        GenerationStats {
+            iteration: 1,
+            generation: generation,
+            players_with_payoff: 2,
+            average_payoff: 1.5,
+            best_payoff: best_payoff,
+            fitness: FitnessSummary::from_fitness(&[1.0, 2.0]),
+            diversity: Diversity {
+                mean_distance: 0.5,
+                niches: 2
+            },
+            trades: 10,
+            unique_screeners: 2,
+            cache_hits: 0,
+            elapsed: 0.25
+        }
+    }
+
+    #[test]
+    fn summarises_fitness() {
+        let odd = FitnessSummary::from_fitness(&[3.0, 1.0, 2.0]);
+        assert_eq!((odd.min, odd.mean, odd.median, odd.max), (1.0, 2.0, 2.0, 3.0));
+        let even = FitnessSummary::from_fitness(&[4.0, 1.0, 2.0, 5.0]);
+        assert_eq!((even.min, even.mean, even.median, even.max), (1.0, 3.0, 3.0, 5.0));
+        let empty = FitnessSummary::from_fitness(&[]);
+        assert_eq!((empty.min, empty.mean, empty.median, empty.max), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn the_log_has_a_row_per_generation() {
+        let directory = std::env::temp_dir().join(format!("game-stats-test-{}", std::process::id()));
+        let path = StatsLog::path_for_output(&directory.join("output-r0.txt"));
+        assert_eq!(path.file_name().unwrap(), "output-r0-stats.csv");
+        let mut log = StatsLog::create(&path).unwrap();
+        log.write(&stats(0, None)).unwrap();
+        log.write(&stats(1, Some(4.0))).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        let rows = contents.lines().map(|line| line.split(',').collect::<Vec<&str>>()).collect::<Vec<Vec<&str>>>();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], GenerationStats::header());
+        assert!(rows.iter().all(|row| row.len() == GenerationStats::header().len()));
+        assert_eq!((rows[1][1], rows[1][4]), ("0", ""));
+        assert_eq!((rows[2][1], rows[2][4]), ("1", "4"));
+    }
+}
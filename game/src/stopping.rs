@@ -0,0 +1,198 @@
+use std::{fmt, time::{Duration, Instant}};
+
+use crate::statistics::GenerationStats;
+
+/// When an iteration stops before its last generation. Every criterion is off by default.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct StoppingSettings {
+    /// Stop once the best payoff hasn't improved for this many generations.
+    pub plateau: Option<usize>,
+    /// The amount, in percent per year, that the best payoff must rise by to count as improving.
+    pub plateau_tolerance: f64,
+    /// Stop once the mean pairwise distance of the population falls below this.
+    pub min_diversity: Option<f64>,
+    /// Stop every iteration once the game has run for this long.
+    pub time_limit: Option<Duration>,
+    /// Stop once a player's payoff reaches this, in percent per year.
+    pub target_fitness: Option<f64>
+}
+
+/// Why an iteration stopped early.
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum StopReason {
+    Plateau(usize),
+    DiversityCollapse(f64),
+    TimeLimit(Duration),
+    TargetFitness(f64)
+}
+
+/// The progress of an iteration, as seen by the stopping criteria.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Convergence {
+    start: Instant,
+    best: Option<f64>,
+    since_improvement: usize
+}
+
+impl fmt::Display for StoppingSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StoppingSettings[plateau: {:?}, plateau_tolerance: {}, min_diversity: {:?}, time_limit: {:?}, target_fitness: {:?}]", self.plateau, self.plateau_tolerance, self.min_diversity, self.time_limit, self.target_fitness)
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Plateau(generations) => write!(f, "best payoff didn't improve for {} generations", generations),
+            StopReason::DiversityCollapse(mean_distance) => write!(f, "diversity collapsed to a mean pairwise distance of {:.3}", mean_distance),
+            StopReason::TimeLimit(elapsed) => write!(f, "time limit reached after {:.3}s", elapsed.as_secs_f64()),
+            StopReason::TargetFitness(payoff) => write!(f, "target fitness reached with a payoff of {:.3}%", payoff)
+        }
+    }
+}
+
+impl StoppingSettings {
+    /// The default settings: every iteration runs for all of its generations.
+    pub fn new_default() -> StoppingSettings {
+        StoppingSettings {
+            plateau: None,
+            plateau_tolerance: 0.0,
+            min_diversity: None,
+            time_limit: None,
+            target_fitness: None
+        }
+    }
+    /// Returns true if the game has run out of time.
+    ///
+    /// # Arguments
+    /// * `start` - When the game started.
+    pub fn is_out_of_time(&self, start: Instant) -> bool {
+        match self.time_limit {
+            Some(limit) => start.elapsed() >= limit,
+            None => false
+        }
+    }
+    /// Checks every criterion after a generation. Returns the first that is met, if any.
+    ///
+    /// # Arguments
+    /// * `convergence` - The progress of the iteration, which is updated.
+    /// * `stats` - The statistics of the generation just performed.
+    pub fn check(&self, convergence: &mut Convergence, stats: &GenerationStats) -> Option<StopReason> {
+        match (stats.best_payoff, convergence.best) {
+            (Some(payoff), Some(best)) if payoff <= best + self.plateau_tolerance => convergence.since_improvement += 1,
+            (None, _) => convergence.since_improvement += 1,
+            (Some(payoff), _) => {
+                convergence.best = Some(payoff);
+                convergence.since_improvement = 0;
+            }
+        }
+        if let (Some(target), Some(payoff)) = (self.target_fitness, stats.best_payoff) {
+            if payoff >= target {
+                return Some(StopReason::TargetFitness(payoff));
+            }
+        }
+        if let Some(plateau) = self.plateau {
+            if convergence.since_improvement >= plateau {
+                return Some(StopReason::Plateau(convergence.since_improvement));
+            }
+        }
+        if let Some(min_diversity) = self.min_diversity {
+            if stats.diversity.mean_distance < min_diversity {
+                return Some(StopReason::DiversityCollapse(stats.diversity.mean_distance));
+            }
+        }
+        if self.is_out_of_time(convergence.start) {
+            return Some(StopReason::TimeLimit(convergence.start.elapsed()));
+        }
+        None
+    }
+}
+
+impl Convergence {
+    /// Starts tracking an iteration of a game.
+    ///
+    /// # Arguments
+    /// * `start` - When the game started, which the time limit is measured from.
+    pub fn new(start: Instant) -> Convergence {
+        Convergence {
+            start: start,
+            best: None,
+            since_improvement: 0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::niching::Diversity;
+    use crate::statistics::FitnessSummary;
+
+    fn stats(best_payoff: Option<f64>, mean_distance: f64) -> GenerationStats {
+        GenerationStats {
+            iteration: 1,
+            generation: 0,
+            players_with_payoff: 0,
+            average_payoff: 0.0,
+            best_payoff: best_payoff,
+            fitness: FitnessSummary::from_fitness(&[]),
+            diversity: Diversity {
+                mean_distance: mean_distance,
+                niches: 1
+            },
+            trades: 0,
+            unique_screeners: 0,
+            cache_hits: 0,
+            elapsed: 0.0
+        }
+    }
+
+    #[test]
+    fn no_criterion_stops_by_default() {
+        let mut convergence = Convergence::new(Instant::now());
+        for _ in 0..10 {
+            assert!(StoppingSettings::new_default().check(&mut convergence, &stats(None, 0.0)).is_none());
+        }
+    }
+
+    #[test]
+    fn a_plateau_stops_at_its_limit() {
+        let settings = StoppingSettings {
+            plateau: Some(2),
+            plateau_tolerance: 0.5,
+            ..StoppingSettings::new_default()
+        };
+        let mut convergence = Convergence::new(Instant::now());
+        assert!(settings.check(&mut convergence, &stats(Some(1.0), 1.0)).is_none());
+        assert!(settings.check(&mut convergence, &stats(Some(2.0), 1.0)).is_none());
+        assert!(settings.check(&mut convergence, &stats(Some(2.4), 1.0)).is_none());
+        assert!(matches!(settings.check(&mut convergence, &stats(None, 1.0)), Some(StopReason::Plateau(2))));
+    }
+
+    #[test]
+    fn the_target_and_diversity_stop_at_their_limits() {
+        let settings = StoppingSettings {
+            target_fitness: Some(10.0),
+            min_diversity: Some(0.1),
+            ..StoppingSettings::new_default()
+        };
+        let mut convergence = Convergence::new(Instant::now());
+        assert!(settings.check(&mut convergence, &stats(Some(9.9), 0.1)).is_none());
+        assert!(matches!(settings.check(&mut convergence, &stats(Some(9.9), 0.09)), Some(StopReason::DiversityCollapse(_))));
+        assert!(matches!(settings.check(&mut convergence, &stats(Some(10.0), 0.5)), Some(StopReason::TargetFitness(_))));
+    }
+
+    #[test]
+    fn a_spent_time_limit_stops() {
+        let settings = StoppingSettings {
+            time_limit: Some(Duration::from_secs(0)),
+            ..StoppingSettings::new_default()
+        };
+        assert!(settings.is_out_of_time(Instant::now()));
+        assert!(matches!(settings.check(&mut Convergence::new(Instant::now()), &stats(None, 1.0)), Some(StopReason::TimeLimit(_))));
+        assert!(!StoppingSettings::new_default().is_out_of_time(Instant::now()));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fmt, collections::HashMap, sync::{OnceLock, RwLock}};
+
+/// An interned stock name. Copying a Ticker is free, and two Tickers are equal exactly when
+/// their names are.
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ticker(u32);
+
+/// The global table of every name that has been interned.
+struct TickerTable {
+    names: Vec<&'static str>,
+    lookup: HashMap<&'static str, u32>
+}
+
+fn ticker_table() -> &'static RwLock<TickerTable> {
+    static TABLE: OnceLock<RwLock<TickerTable>> = OnceLock::new();
+    TABLE.get_or_init(|| RwLock::new(TickerTable {
+        names: Vec::new(),
+        lookup: HashMap::new()
+    }))
+}
+
+impl fmt::Display for Ticker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Ticker {
+    /// Returns the Ticker for a name, adding it to the global table if it's new.
+    ///
+    /// # Arguments
+    /// * `name` - The stock name.
+    ///
+    /// # Remarks
+    /// Interned names live for the rest of the program.
+    pub fn intern(name: &str) -> Ticker {
+        if let Some(index) = ticker_table().read().unwrap().lookup.get(name) {
+            return Ticker(*index);
+        }
+        let mut table = ticker_table().write().unwrap();
+        if let Some(index) = table.lookup.get(name) {
+            return Ticker(*index);
+        }
+        let index = table.names.len() as u32;
+        let leaked: &'static str = Box::leak(name.to_string().into_boxed_str());
+        table.names.push(leaked);
+        table.lookup.insert(leaked, index);
+        Ticker(index)
+    }
+    /// Returns the Ticker for a name if it has already been interned.
+    ///
+    /// # Arguments
+    /// * `name` - The stock name.
+    pub fn find(name: &str) -> Option<Ticker> {
+        ticker_table().read().unwrap().lookup.get(name).map(|index| Ticker(*index))
+    }
+    /// Returns the name of the Ticker.
+    pub fn name(&self) -> &'static str {
+        ticker_table().read().unwrap().names[self.0 as usize]
+    }
+}
@@ -0,0 +1,269 @@
+use std::{fmt, collections::{HashMap, HashSet}, fs::read_to_string, path::PathBuf};
+use csv::Reader;
+
+use crate::error::Error;
+use crate::quarters::Quarters;
+use crate::ticker::Ticker;
+
+/// The definition of which stocks are tradeable, applied to every quarter before any Screener
+/// is checked.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Universe {
+    pub min_market_cap: Option<f64>,
+    pub min_price: Option<f64>,
+    pub min_volume: Option<f64>,
+    pub market_cap_field: String,
+    pub price_field: String,
+    pub volume_field: String,
+    pub tickers: Option<Vec<String>>,
+    pub exchanges: Option<Vec<String>>,
+    pub exchange_map: HashMap<String, String>,
+    pub excluded: Vec<String>,
+    pub min_history: usize
+}
+
+/// What a Universe removed from a Quarters set.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct UniverseReport {
+    pub quarter_counts: Vec<(String, usize, usize)>,
+    pub market_cap_fails: usize,
+    pub price_fails: usize,
+    pub volume_fails: usize,
+    pub ticker_fails: usize,
+    pub exchange_fails: usize,
+    pub excluded_fails: usize,
+    pub history_fails: usize
+}
+
+impl fmt::Display for Universe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Universe[min_market_cap: {:?} ({}), min_price: {:?} ({}), min_volume: {:?} ({}), tickers: {:?}, exchanges: {:?}, excluded: {}, min_history: {}]", self.min_market_cap, self.market_cap_field, self.min_price, self.price_field, self.min_volume, self.volume_field, self.tickers.as_ref().map(|tickers| tickers.len()), self.exchanges, self.excluded.len(), self.min_history)
+    }
+}
+
+impl fmt::Display for UniverseReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (eligible, total) = self.quarter_counts.iter().fold((0, 0), |(acc_e, acc_t), (_, e, t)| (acc_e + e, acc_t + t));
+        write!(f, "UniverseReport[eligible: {} of {} records over {} quarters, market_cap_fails: {}, price_fails: {}, volume_fails: {}, ticker_fails: {}, exchange_fails: {}, excluded_fails: {}, history_fails: {}]", eligible, total, self.quarter_counts.len(), self.market_cap_fails, self.price_fails, self.volume_fails, self.ticker_fails, self.exchange_fails, self.excluded_fails, self.history_fails)
+    }
+}
+
+impl Universe {
+    /// Creates a Universe where every stock is eligible.
+    pub fn new_default() -> Universe {
+        Universe {
+            min_market_cap: None,
+            min_price: None,
+            min_volume: None,
+            market_cap_field: "marketcap".to_string(),
+            price_field: "close".to_string(),
+            volume_field: "volume".to_string(),
+            tickers: None,
+            exchanges: None,
+            exchange_map: HashMap::new(),
+            excluded: Vec::new(),
+            min_history: 0
+        }
+    }
+    /// Returns true if this Universe doesn't remove anything.
+    pub fn is_default(&self) -> bool {
+        self.min_market_cap.is_none() & self.min_price.is_none() & self.min_volume.is_none() & self.tickers.is_none() & self.exchanges.is_none() & self.excluded.is_empty() & (self.min_history == 0)
+    }
+    /// Reads a list of tickers from a file, one per line (commas are also accepted).
+    ///
+    /// # Arguments
+    /// * `file_name` - The path of the file to read.
+    pub fn read_ticker_list(file_name: &str) -> Result<Vec<String>, Error> {
+        match read_to_string(file_name) {
+            Ok(contents) => Ok(contents.split(|c| (c == '\n') | (c == ',')).map(|ticker| ticker.trim().to_string()).filter(|ticker| !ticker.is_empty()).collect()),
+            Err(why) => Err(Error::Io(PathBuf::from(file_name), why))
+        }
+    }
+    /// Reads a map of ticker to exchange from a CSV file with the columns "ticker" and
+    /// "exchange".
+    ///
+    /// # Arguments
+    /// * `file_name` - The path of the file to read.
+    pub fn read_exchange_map(file_name: &str) -> Result<HashMap<String, String>, Error> {
+        let mut reader = Reader::from_path(file_name).map_err(|why| Error::Csv(PathBuf::from(file_name), why))?;
+        let headers = reader.headers().map_err(|why| Error::Csv(PathBuf::from(file_name), why))?.clone();
+        let find_column = |name: &str| headers.iter().position(|header| header == name).ok_or_else(|| Error::Schema(format!("the exchange map {:?} has no {:?} column.", file_name, name)));
+        let ticker_index = find_column("ticker")?;
+        let exchange_index = find_column("exchange")?;
+        Ok(reader.records().filter_map(|row| row.ok()).filter_map(|row| {
+            match (row.get(ticker_index), row.get(exchange_index)) {
+                (Some(ticker), Some(exchange)) => Some((ticker.to_string(), exchange.to_string())),
+                _ => None
+            }
+        }).collect())
+    }
+    /// Computes which records of every quarter are in the Universe.
+    ///
+    /// # Arguments
+    /// * `quarters` - The raw data to check the Universe against.
+    ///
+    /// # Remarks
+    /// The output is indexed in the same way as `quarters`, [quarter][record]. A record that
+    /// has no value for a field with a minimum is not eligible. Returns an error if a field with
+    /// a minimum doesn't exist in the data, or if there are exchanges but no exchange map, since
+    /// no stock could be eligible.
+    pub fn eligibility(&self, quarters: &Quarters<f64>) -> Result<(Vec<Vec<bool>>, UniverseReport), Error> {
+        let find_field = |minimum: &Option<f64>, name: &String| -> Result<Option<(usize, f64)>, Error> {
+            match minimum {
+                Some(minimum) => match quarters.field_names.iter().position(|field_name| field_name == name) {
+                    Some(index) => Ok(Some((index, *minimum))),
+                    None => Err(Error::Config(format!("Universe field {:?} doesn't exist in the data.", name)))
+                },
+                None => Ok(None)
+            }
+        };
+        let market_cap = find_field(&self.min_market_cap, &self.market_cap_field)?;
+        let price = find_field(&self.min_price, &self.price_field)?;
+        let volume = find_field(&self.min_volume, &self.volume_field)?;
+        if self.exchanges.is_some() && self.exchange_map.is_empty() {
+            return Err(Error::Config("Universe exchanges are set, but the exchange map is empty.".to_string()));
+        }
+        let mut report = UniverseReport {
+            quarter_counts: Vec::new(),
+            market_cap_fails: 0,
+            price_fails: 0,
+            volume_fails: 0,
+            ticker_fails: 0,
+            exchange_fails: 0,
+            excluded_fails: 0,
+            history_fails: 0
+        };
+        let tickers = self.tickers.as_ref().map(|tickers| tickers.iter().map(|name| Ticker::intern(name)).collect::<HashSet<Ticker>>());
+        let excluded = self.excluded.iter().map(|name| Ticker::intern(name)).collect::<HashSet<Ticker>>();
+        let mut history: HashMap<Ticker, usize> = HashMap::new();
+        let mut eligibility = Vec::new();
+        for quarter in quarters.iter() {
+            let mut quarter_eligibility = Vec::new();
+            for stock in quarter.iter() {
+                let name = stock.stock_id.name;
+                let seen = history.entry(name).or_insert(0);
+                *seen += 1;
+                let below = |limit: &Option<(usize, f64)>| match limit {
+                    Some((index, minimum)) => !matches!(stock.get(*index), Some(value) if value >= *minimum),
+                    None => false
+                };
+                let eligible = if below(&market_cap) {
+                    report.market_cap_fails += 1;
+                    false
+                } else if below(&price) {
+                    report.price_fails += 1;
+                    false
+                } else if below(&volume) {
+                    report.volume_fails += 1;
+                    false
+                } else if !tickers.as_ref().is_none_or(|tickers| tickers.contains(&name)) {
+                    report.ticker_fails += 1;
+                    false
+                } else if !self.exchanges.as_ref().is_none_or(|exchanges| self.exchange_map.get(name.name()).is_some_and(|exchange| exchanges.contains(exchange))) {
+                    report.exchange_fails += 1;
+                    false
+                } else if excluded.contains(&name) {
+                    report.excluded_fails += 1;
+                    false
+                } else if *seen < self.min_history {
+                    report.history_fails += 1;
+                    false
+                } else {
+                    true
+                };
+                quarter_eligibility.push(eligible);
+            }
+            report.quarter_counts.push((quarter.time_id.to_string(), quarter_eligibility.iter().filter(|eligible| **eligible).count(), quarter.len()));
+            eligibility.push(quarter_eligibility);
+        }
+        Ok((eligibility, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::{DataRecord, StockID, TimeID};
+    use crate::quarter::Quarter;
+
+    /// Quarters of "close" from (period, ticker, close) records, in period order.
+    fn quarters(records: &[(&str, &str, Option<f64>)]) -> Quarters<f64> {
+        let mut quarters_vector: Vec<Quarter<f64>> = Vec::new();
+        for (period, ticker, close) in records {
+            let time_id = TimeID::from_name(period).unwrap();
+            if quarters_vector.last().is_none_or(|quarter| quarter.time_id.index() != time_id.index()) {
+                quarters_vector.push(Quarter::new(Vec::new(), time_id));
+            }
+            quarters_vector.last_mut().unwrap().push(DataRecord {
+                record: vec![*close],
+                stock_id: StockID {
+                    name: Ticker::intern(ticker),
+                    time_id: time_id,
+                    iteration: 0
+                }
+            });
+        }
+        Quarters {
+            field_names: vec!["close".to_string()],
+            starting_time: quarters_vector[0].time_id,
+            ending_time: quarters_vector[quarters_vector.len() - 1].time_id,
+            quarters_vector: quarters_vector
+        }
+    }
+
+    fn sample() -> Quarters<f64> {
+        quarters(&[
+            ("2010-Q1", "UA", Some(10.0)),
+            ("2010-Q1", "UB", Some(1.0)),
+            ("2010-Q2", "UA", Some(12.0)),
+            ("2010-Q2", "UB", None),
+            ("2010-Q2", "UC", Some(20.0))
+        ])
+    }
+
+    #[test]
+    fn the_default_universe_has_every_stock() {
+        let (eligibility, report) = Universe::new_default().eligibility(&sample()).unwrap();
+        assert_eq!(eligibility, vec![vec![true, true], vec![true, true, true]]);
+        assert_eq!(report.quarter_counts[1], ("2010-2".to_string(), 3, 3));
+    }
+
+    #[test]
+    fn stocks_below_a_minimum_or_without_a_value_are_removed() {
+        let mut universe = Universe::new_default();
+        universe.min_price = Some(5.0);
+        let (eligibility, report) = universe.eligibility(&sample()).unwrap();
+        assert_eq!(eligibility, vec![vec![true, false], vec![true, false, true]]);
+        assert_eq!(report.price_fails, 2);
+        universe.price_field = "price".to_string();
+        assert!(matches!(universe.eligibility(&sample()), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn tickers_exclusions_and_history_are_applied() {
+        let mut universe = Universe::new_default();
+        universe.tickers = Some(vec!["UA".to_string(), "UB".to_string()]);
+        universe.excluded = vec!["UB".to_string()];
+        let (eligibility, report) = universe.eligibility(&sample()).unwrap();
+        assert_eq!(eligibility, vec![vec![true, false], vec![true, false, false]]);
+        assert_eq!((report.ticker_fails, report.excluded_fails), (1, 2));
+        let mut universe = Universe::new_default();
+        universe.min_history = 2;
+        let (eligibility, report) = universe.eligibility(&sample()).unwrap();
+        assert_eq!(eligibility, vec![vec![false, false], vec![true, true, false]]);
+        assert_eq!(report.history_fails, 3);
+    }
+
+    #[test]
+    fn exchanges_need_an_exchange_map() {
+        let mut universe = Universe::new_default();
+        universe.exchanges = Some(vec!["NYSE".to_string()]);
+        assert!(matches!(universe.eligibility(&sample()), Err(Error::Config(_))));
+        universe.exchange_map = [("UA", "NYSE"), ("UB", "NASDAQ")].iter().map(|(ticker, exchange)| (ticker.to_string(), exchange.to_string())).collect();
+        let (eligibility, report) = universe.eligibility(&sample()).unwrap();
+        assert_eq!(eligibility, vec![vec![true, false], vec![true, false, false]]);
+        assert_eq!(report.exchange_fails, 3);
+    }
+}
@@ -0,0 +1,431 @@
+use std::{fmt, collections::{BTreeMap, HashMap, HashSet}};
+
+use crate::quarters::Quarters;
+use crate::ticker::Ticker;
+
+/// The field added to every record when offending data is flagged.
+pub static QUALITY_FIELD: &str = "quality_issues";
+/// The fewest values of a field in a period that outliers are looked for among.
+pub static MIN_OUTLIER_SAMPLES: usize = 5;
+/// Scales the median absolute deviation to the standard deviation of normally distributed data.
+static MAD_SCALE: f64 = 0.6745;
+
+/// What is done with the offending data that validation finds.
+#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum ValidationAction {
+    /// Only report it.
+    Report,
+    /// Add a QUALITY_FIELD to every record, holding its number of issues.
+    Flag,
+    /// Empty outlying and negative values, and remove duplicate records and records whose value
+    /// field is outlying, negative or empty.
+    Drop
+}
+
+/// The checks made on the loaded data before it's normalised.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ValidationSettings {
+    pub enabled: bool,
+    pub action: ValidationAction,
+    /// A value is an outlier if its modified z-score, from the median and median absolute
+    /// deviation of its field in its period, is above this.
+    pub outlier_threshold: f64,
+    /// A field with values in fewer than this fraction of records is reported as mostly empty.
+    pub min_coverage: f64,
+    /// The fields that can't be negative. Without them, every field with "shares" in its name.
+    pub non_negative_fields: Option<Vec<String>>,
+    pub price_field: String,
+    /// A price that rises or falls by more than this factor from the previous period is
+    /// reported as a jump, like a split that wasn't adjusted for.
+    pub max_price_ratio: f64
+}
+
+/// The quality of one field over every period.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct FieldQuality {
+    pub name: String,
+    pub records: usize,
+    pub values: usize,
+    pub outliers: usize,
+    pub negatives: usize
+}
+
+/// The quality of one stock over every period.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct StockQuality {
+    pub ticker: String,
+    pub periods: usize,
+    /// The number of periods missing between the stock's first and last period.
+    pub gaps: usize,
+    /// The number of records of a period the stock already had a record for.
+    pub duplicates: usize,
+    pub outliers: usize,
+    pub negatives: usize,
+    pub price_jumps: usize
+}
+
+/// What validation found, by field and by stock.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ValidationReport {
+    pub action: ValidationAction,
+    pub min_coverage: f64,
+    pub fields: Vec<FieldQuality>,
+    pub stocks: Vec<StockQuality>
+}
+
+impl fmt::Display for ValidationAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationAction::Report => write!(f, "Report"),
+            ValidationAction::Flag => write!(f, "Flag"),
+            ValidationAction::Drop => write!(f, "Drop")
+        }
+    }
+}
+
+impl fmt::Display for ValidationSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ValidationSettings[enabled: {}, action: {}, outlier_threshold: {}, min_coverage: {}, non_negative_fields: {:?}, price_field: {:?}, max_price_ratio: {}]", self.enabled, self.action, self.outlier_threshold, self.min_coverage, self.non_negative_fields, self.price_field, self.max_price_ratio)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mostly_empty = self.fields.iter().filter(|field| field.is_mostly_empty(self.min_coverage)).count();
+        let sum = |count: fn(&StockQuality) -> usize| self.stocks.iter().map(count).sum::<usize>();
+        write!(f, "ValidationReport[action: {}, fields: {}, mostly_empty: {}, stocks: {}, outliers: {}, negatives: {}, duplicates: {}, gaps: {}, price_jumps: {}]", self.action, self.fields.len(), mostly_empty, self.stocks.len(), sum(|stock| stock.outliers), sum(|stock| stock.negatives), sum(|stock| stock.duplicates), sum(|stock| stock.gaps), sum(|stock| stock.price_jumps))
+    }
+}
+
+impl ValidationAction {
+    /// Parse a ValidationAction from its command line name.
+    ///
+    /// # Arguments
+    /// * `name` - One of "report", "flag" or "drop".
+    pub fn from_name(name: &str) -> Option<ValidationAction> {
+        match name {
+            "report" => Some(ValidationAction::Report),
+            "flag" => Some(ValidationAction::Flag),
+            "drop" => Some(ValidationAction::Drop),
+            _ => None
+        }
+    }
+}
+
+impl ValidationSettings {
+    /// The default settings: no validation, and when it's enabled only a report, of values more
+    /// than 5 modified z-scores out, fields with values in under half of the records, and prices
+    /// that move tenfold.
+    pub fn new_default() -> ValidationSettings {
+        ValidationSettings {
+            enabled: false,
+            action: ValidationAction::Report,
+            outlier_threshold: 5.0,
+            min_coverage: 0.5,
+            non_negative_fields: None,
+            price_field: "close".to_string(),
+            max_price_ratio: 10.0
+        }
+    }
+    /// Returns true if a field can't be negative.
+    fn is_non_negative(&self, name: &str) -> bool {
+        match &self.non_negative_fields {
+            Some(names) => names.iter().any(|non_negative| non_negative == name),
+            None => name.contains("shares")
+        }
+    }
+    /// Checks every record of some Quarters, then flags or drops the offending data as set.
+    ///
+    /// # Arguments
+    /// * `quarters` - The raw Quarters, before they're normalised.
+    /// * `value_index` - The index of the field used to value trades, which every record needs.
+    ///
+    /// # Remarks
+    /// Outliers are found among the values of a field within one period, so a field that grows
+    /// over the years isn't all outlying in its early and late periods. Periods with fewer than
+    /// MIN_OUTLIER_SAMPLES values of a field, or with a median absolute deviation of zero, have no
+    /// outliers in that field. Price jumps and gaps are only reported, since they can be genuine.
+    pub fn validate(&self, quarters: &mut Quarters<f64>, value_index: usize) -> ValidationReport {
+        let field_count = quarters.field_names.len();
+        let non_negative = quarters.field_names.iter().map(|name| self.is_non_negative(name)).collect::<Vec<bool>>();
+        let price_index = quarters.field_names.iter().position(|name| *name == self.price_field);
+        let mut fields = quarters.field_names.iter().map(|name| FieldQuality {
+            name: name.clone(),
+            records: 0,
+            values: 0,
+            outliers: 0,
+            negatives: 0
+        }).collect::<Vec<FieldQuality>>();
+        let mut stocks: BTreeMap<&str, StockQuality> = BTreeMap::new();
+        let mut stock_periods: HashMap<Ticker, Vec<i64>> = HashMap::new();
+        let mut last_prices: HashMap<Ticker, (i64, f64)> = HashMap::new();
+        // The offending values of each record, and whether it's a duplicate, by period
+        let mut issues = Vec::with_capacity(quarters.len());
+        for quarter in quarters.iter() {
+            let spreads = (0..field_count).map(|i| {
+                let values = quarter.iter().filter_map(|record| record.get(i)).collect::<Vec<f64>>();
+                median_and_mad(values)
+            }).collect::<Vec<Option<(f64, f64)>>>();
+            let period = quarter.time_id.index();
+            let mut seen = HashSet::new();
+            let mut quarter_issues = Vec::with_capacity(quarter.len());
+            for record in quarter.iter() {
+                let name = record.stock_id.name;
+                let stock = stocks.entry(name.name()).or_insert_with(|| StockQuality::new_empty(name.name()));
+                let duplicate = !seen.insert(name);
+                if duplicate {
+                    stock.duplicates += 1;
+                } else {
+                    stock_periods.entry(name).or_default().push(period);
+                }
+                let mut offending = Vec::new();
+                for (i, field) in fields.iter_mut().enumerate() {
+                    field.records += 1;
+                    let value = match record.get(i) {
+                        Some(value) => value,
+                        None => continue
+                    };
+                    field.values += 1;
+                    if non_negative[i] && (value < 0.0) {
+                        field.negatives += 1;
+                        stock.negatives += 1;
+                        offending.push(i);
+                    } else if let Some((median, mad)) = spreads[i] {
+                        if (MAD_SCALE * (value - median) / mad).abs() > self.outlier_threshold {
+                            field.outliers += 1;
+                            stock.outliers += 1;
+                            offending.push(i);
+                        }
+                    }
+                }
+                let mut price_jump = false;
+                if let Some(price) = price_index.and_then(|index| record.get(index)).filter(|price| *price > 0.0) {
+                    if let Some((last_period, last_price)) = last_prices.insert(name, (period, price)) {
+                        if (last_period + 1 == period) && ((price / last_price).max(last_price / price) > self.max_price_ratio) {
+                            stock.price_jumps += 1;
+                            price_jump = true;
+                        }
+                    }
+                }
+                quarter_issues.push((offending, duplicate, price_jump));
+            }
+            issues.push(quarter_issues);
+        }
+        for (name, periods) in stock_periods {
+            let (first, last) = (periods.iter().min().unwrap(), periods.iter().max().unwrap());
+            let stock = stocks.get_mut(name.name()).unwrap();
+            stock.periods = periods.len();
+            stock.gaps = (last - first + 1) as usize - periods.len();
+        }
+        match self.action {
+            ValidationAction::Report => {},
+            ValidationAction::Flag => {
+                quarters.field_names.push(QUALITY_FIELD.to_string());
+                for (quarter, quarter_issues) in quarters.quarters_vector.iter_mut().zip(issues.iter()) {
+                    for (stock, (offending, duplicate, price_jump)) in quarter.records_mut().zip(quarter_issues.iter()) {
+                        stock.record.push(Some((offending.len() + (*duplicate as usize) + (*price_jump as usize)) as f64));
+                    }
+                }
+            },
+            ValidationAction::Drop => {
+                for (quarter, quarter_issues) in quarters.quarters_vector.iter_mut().zip(issues.iter()) {
+                    for (stock, (offending, _, _)) in quarter.records_mut().zip(quarter_issues.iter()) {
+                        for i in offending {
+                            stock.record[*i] = None;
+                        }
+                    }
+                    let mut removed = quarter_issues.iter().map(|(offending, duplicate, _)| *duplicate || offending.contains(&value_index));
+                    quarter.retain(|record| !removed.next().unwrap_or(false) && record.get(value_index).is_some());
+                }
+            }
+        }
+        ValidationReport {
+            action: self.action,
+            min_coverage: self.min_coverage,
+            fields: fields,
+            stocks: stocks.into_values().collect()
+        }
+    }
+}
+
+impl FieldQuality {
+    /// Returns the fraction of records with a value of the field.
+    pub fn coverage(&self) -> f64 {
+        if self.records == 0 {0.0} else {(self.values as f64) / (self.records as f64)}
+    }
+    /// Returns true if the field has values in fewer than min_coverage of the records.
+    pub fn is_mostly_empty(&self, min_coverage: f64) -> bool {
+        self.coverage() < min_coverage
+    }
+}
+
+impl StockQuality {
+    /// Creates the quality of a stock with no records.
+    fn new_empty(ticker: &str) -> StockQuality {
+        StockQuality {
+            ticker: ticker.to_string(),
+            periods: 0,
+            gaps: 0,
+            duplicates: 0,
+            outliers: 0,
+            negatives: 0,
+            price_jumps: 0
+        }
+    }
+    /// Returns true if anything was found wrong with the stock.
+    pub fn has_issues(&self) -> bool {
+        (self.gaps + self.duplicates + self.outliers + self.negatives + self.price_jumps) > 0
+    }
+}
+
+impl ValidationReport {
+    /// Returns a line for each field, and for each stock that has issues.
+    pub fn lines(&self) -> Vec<String> {
+        let field_lines = self.fields.iter().map(|field| {
+            format!("Validation {}: {:.1}% coverage{}, {} outliers, {} negative.", field.name, 100.0 * field.coverage(), if field.is_mostly_empty(self.min_coverage) {" (mostly empty)"} else {""}, field.outliers, field.negatives)
+        });
+        let stock_lines = self.stocks.iter().filter(|stock| stock.has_issues()).map(|stock| {
+            format!("Validation {}: {} periods, {} gaps, {} duplicates, {} outliers, {} negative, {} price jumps.", stock.ticker, stock.periods, stock.gaps, stock.duplicates, stock.outliers, stock.negatives, stock.price_jumps)
+        });
+        field_lines.chain(stock_lines).collect()
+    }
+}
+
+/// Returns the median and median absolute deviation of some values, or None if there are too
+/// few values or they don't deviate.
+fn median_and_mad(mut values: Vec<f64>) -> Option<(f64, f64)> {
+    if values.len() < MIN_OUTLIER_SAMPLES {
+        return None;
+    }
+    let median = median_of(&mut values);
+    let mut deviations = values.iter().map(|value| (value - median).abs()).collect::<Vec<f64>>();
+    let mad = median_of(&mut deviations);
+    if mad > 0.0 {Some((median, mad))} else {None}
+}
+
+/// Returns the median of some values, sorting them.
+fn median_of(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {(values[middle - 1] + values[middle]) / 2.0} else {values[middle]}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_record::{DataRecord, StockID, TimeID};
+    use crate::quarter::Quarter;
+
+    /// Quarters of "close" and "shares" from (period, ticker, close, shares) records.
+    fn quarters(records: &[(&str, &str, Option<f64>, Option<f64>)]) -> Quarters<f64> {
+        let mut periods: BTreeMap<i64, Vec<DataRecord<f64>>> = BTreeMap::new();
+        for (period, ticker, close, shares) in records {
+            let time_id = TimeID::from_name(period).unwrap();
+            periods.entry(time_id.index()).or_default().push(DataRecord {
+                record: vec![*close, *shares],
+                stock_id: StockID {
+                    name: Ticker::intern(ticker),
+                    time_id: time_id,
+                    iteration: 0
+                }
+            });
+        }
+        let quarters_vector = periods.into_values().map(|records| {
+            let time_id = records[0].stock_id.time_id;
+            Quarter::new(records, time_id)
+        }).collect::<Vec<Quarter<f64>>>();
+        Quarters {
+            field_names: vec!["close".to_string(), "shares".to_string()],
+            starting_time: quarters_vector[0].time_id,
+            ending_time: quarters_vector[quarters_vector.len() - 1].time_id,
+            quarters_vector: quarters_vector
+        }
+    }
+
+    /// One period of closes, each of a different stock.
+    fn closes(values: &[f64]) -> Quarters<f64> {
+        let tickers = ["VA", "VB", "VC", "VD", "VE", "VF"];
+        quarters(&values.iter().zip(tickers.iter()).map(|(close, ticker)| ("2010-Q1", *ticker, Some(*close), Some(1.0))).collect::<Vec<_>>())
+    }
+
+    fn enabled(action: ValidationAction) -> ValidationSettings {
+        let mut settings = ValidationSettings::new_default();
+        settings.enabled = true;
+        settings.action = action;
+        settings
+    }
+
+    fn stock<'a>(report: &'a ValidationReport, ticker: &str) -> &'a StockQuality {
+        report.stocks.iter().find(|stock| stock.ticker == ticker).unwrap()
+    }
+
+    #[test]
+    fn outliers_are_found_by_median_absolute_deviation() {
+        let report = enabled(ValidationAction::Report).validate(&mut closes(&[10.0, 11.0, 12.0, 13.0, 14.0, 1000.0]), 0);
+        assert_eq!(report.fields[0].outliers, 1);
+        assert_eq!(stock(&report, "VF").outliers, 1);
+        assert_eq!(stock(&report, "VE").outliers, 0);
+    }
+
+    #[test]
+    fn too_few_or_undeviating_values_have_no_outliers() {
+        let too_few = enabled(ValidationAction::Report).validate(&mut closes(&[10.0, 11.0, 12.0, 1000.0]), 0);
+        assert_eq!(too_few.fields[0].outliers, 0);
+        let no_deviation = enabled(ValidationAction::Report).validate(&mut closes(&[10.0, 10.0, 10.0, 10.0, 10.0, 1000.0]), 0);
+        assert_eq!(no_deviation.fields[0].outliers, 0);
+    }
+
+    #[test]
+    fn duplicates_gaps_and_price_jumps_are_counted() {
+        let mut quarters = quarters(&[
+            ("2010-Q1", "VG", Some(10.0), None),
+            ("2010-Q1", "VG", Some(10.0), None),
+            ("2010-Q3", "VG", Some(500.0), None),
+            ("2010-Q1", "VH", Some(10.0), None),
+            ("2010-Q2", "VH", Some(200.0), None),
+            ("2010-Q3", "VH", Some(100.0), None)
+        ]);
+        let report = enabled(ValidationAction::Report).validate(&mut quarters, 0);
+        let (gapped, jumping) = (stock(&report, "VG"), stock(&report, "VH"));
+        assert_eq!((gapped.periods, gapped.gaps, gapped.duplicates, gapped.price_jumps), (2, 1, 1, 0));
+        assert_eq!((jumping.periods, jumping.gaps, jumping.duplicates, jumping.price_jumps), (3, 0, 0, 1));
+        assert_eq!(report.fields[1].coverage(), 0.0);
+        assert_eq!(quarters.quarters_vector[0].len(), 3);
+    }
+
+    #[test]
+    fn flagging_adds_the_number_of_issues_to_every_record() {
+        let mut quarters = quarters(&[
+            ("2010-Q1", "VI", Some(10.0), Some(-5.0)),
+            ("2010-Q1", "VI", Some(10.0), Some(1.0)),
+            ("2010-Q1", "VJ", Some(10.0), Some(1.0))
+        ]);
+        enabled(ValidationAction::Flag).validate(&mut quarters, 0);
+        assert_eq!(quarters.field_names.last().map(|name| name.as_str()), Some(QUALITY_FIELD));
+        let flags = quarters.quarters_vector[0].iter().map(|record| record.get(2)).collect::<Vec<Option<f64>>>();
+        assert_eq!(flags, vec![Some(1.0), Some(1.0), Some(0.0)]);
+    }
+
+    #[test]
+    fn dropping_empties_bad_values_and_removes_unvalued_records() {
+        let mut quarters = quarters(&[
+            ("2010-Q1", "VK", Some(10.0), Some(-5.0)),
+            ("2010-Q1", "VK", Some(10.0), Some(1.0)),
+            ("2010-Q1", "VL", Some(-1.0), Some(1.0)),
+            ("2010-Q1", "VM", None, Some(1.0)),
+            ("2010-Q1", "VN", Some(12.0), Some(1.0))
+        ]);
+        let mut settings = enabled(ValidationAction::Drop);
+        settings.non_negative_fields = Some(vec!["close".to_string(), "shares".to_string()]);
+        settings.validate(&mut quarters, 0);
+        let kept = quarters.quarters_vector[0].iter().map(|record| (record.stock_id.name.name(), record.get(1))).collect::<Vec<(&str, Option<f64>)>>();
+        assert_eq!(kept, vec![("VK", None), ("VN", Some(1.0))]);
+        assert!(quarters.quarters_vector[0].contains_ticker(&Ticker::intern("VK")));
+        assert!(!quarters.quarters_vector[0].contains_ticker(&Ticker::intern("VM")));
+    }
+}